@@ -0,0 +1,220 @@
+use crate::layout::AlphaToDisplay;
+use crate::palette::{ColorMode, Palette, Rgba};
+
+// Frame rate of the Raylib window and of exported animations.
+pub const DEFAULT_FPS: u32 = 24;
+
+// Pause at the end of each loop, in rings' worth of time, so the
+// completed pattern stays on display for a moment before starting over.
+const HOLD_RINGS: f32 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimationMode {
+    // Static pattern, the default.
+    Still,
+    // Rings appear one at a time, from the center outwards.
+    Reveal,
+    // Like Reveal, but every ring fades in smoothly.
+    Fade,
+    // The whole pattern is shown, with the palette colors rotating outwards.
+    PaletteCycle,
+}
+
+impl AnimationMode {
+    pub const NAMES: [&'static str; 4] = ["still", "reveal", "fade", "palette"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "still" => Some(AnimationMode::Still),
+            "reveal" => Some(AnimationMode::Reveal),
+            "fade" => Some(AnimationMode::Fade),
+            "palette" => Some(AnimationMode::PaletteCycle),
+            _ => None,
+        }
+    }
+}
+
+/// Time-driven state of an animation. It only decides what every glyph
+/// looks like at a given moment, so any backend can play it back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Animator {
+    pub mode: AnimationMode,
+    // Rings per second, for revealing rings or rotating palette colors.
+    pub speed: f32,
+    pub looping: bool,
+    elapsed: f32,
+}
+
+impl Animator {
+    pub fn new(mode: AnimationMode, speed: f32, looping: bool) -> Self {
+        Self {
+            mode,
+            speed: speed.max(0.0),
+            looping,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn still() -> Self {
+        Self::new(AnimationMode::Still, 0.0, false)
+    }
+
+    /// Advances the animation by dt seconds, typically the duration of one frame.
+    pub fn update(&mut self, dt: f32) {
+        self.elapsed += dt;
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    pub fn set_elapsed(&mut self, elapsed: f32) {
+        self.elapsed = elapsed.max(0.0);
+    }
+
+    /// Seconds it takes to play one loop of the animation, hold included.
+    pub fn cycle_duration(&self, rings: usize) -> f32 {
+        if self.speed <= 0.0 {
+            return 0.0;
+        }
+        match self.mode {
+            AnimationMode::Still => 0.0,
+            AnimationMode::Reveal | AnimationMode::Fade => (rings as f32 + HOLD_RINGS) / self.speed,
+            AnimationMode::PaletteCycle => rings as f32 / self.speed,
+        }
+    }
+
+    // How many rings the animation has progressed, wrapped when looping.
+    fn progress(&self, rings: usize) -> f32 {
+        let progress = self.elapsed * self.speed;
+        let cycle = match self.mode {
+            AnimationMode::PaletteCycle => rings as f32,
+            _ => rings as f32 + HOLD_RINGS,
+        };
+
+        if self.looping && cycle > 0.0 {
+            progress % cycle
+        } else {
+            progress
+        }
+    }
+
+    /// Opacity of a ring, from 0.0 (hidden) to 1.0 (fully shown).
+    pub fn ring_alpha(&self, ring: usize, rings: usize) -> f32 {
+        let progress = self.progress(rings);
+
+        match self.mode {
+            AnimationMode::Reveal => {
+                if (ring as f32) < progress.floor() + 1.0 { 1.0 } else { 0.0 }
+            }
+            AnimationMode::Fade => (progress - ring as f32).clamp(0.0, 1.0),
+            AnimationMode::Still | AnimationMode::PaletteCycle => 1.0,
+        }
+    }
+
+    /// Number of steps the palette has rotated outwards.
+    pub fn palette_shift(&self, rings: usize) -> usize {
+        match self.mode {
+            AnimationMode::PaletteCycle => self.progress(rings).floor() as usize,
+            _ => 0,
+        }
+    }
+
+    /// Color of a glyph at the current moment: the palette color, rotated
+    /// and faded according to the animation mode.
+    pub fn glyph_color(&self, palette: &Palette, color_mode: ColorMode, glyph: &AlphaToDisplay, rings: usize) -> Rgba {
+        self.color_at(palette, color_mode, glyph.ring, glyph.letter, rings)
+    }
+
+    /// Color of whatever sits on the given ring and holds the letter at the
+    /// given alphabet index, a glyph or a cell of the text grid.
+    pub fn color_at(&self, palette: &Palette, color_mode: ColorMode, ring: usize, letter: usize, rings: usize) -> Rgba {
+        // Rotating outwards, every ring takes the color the ring inside it
+        // had a step before, and the center the color of the outer ring.
+
+        let shift = self.palette_shift(rings);
+        let shifted_ring = if rings > 0 { (ring + rings - shift % rings) % rings } else { ring };
+        let letters = palette.stops.len().max(1);
+        let color = palette.glyph_color(color_mode, shifted_ring, letter + letters - shift % letters, rings);
+
+        color.fade(self.ring_alpha(ring, rings))
+    }
+}
+
+impl Default for Animator {
+    fn default() -> Self {
+        Self::still()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reveal() {
+        let mut animator = Animator::new(AnimationMode::Reveal, 2.0, false);
+
+        assert_eq!(1.0, animator.ring_alpha(0, 4));
+        assert_eq!(0.0, animator.ring_alpha(1, 4));
+        animator.update(0.5);
+        assert_eq!(1.0, animator.ring_alpha(1, 4));
+        assert_eq!(0.0, animator.ring_alpha(2, 4));
+        animator.update(100.0);
+        assert_eq!(1.0, animator.ring_alpha(3, 4));
+    }
+
+    #[test]
+    fn test_fade_loop() {
+        let mut animator = Animator::new(AnimationMode::Fade, 1.0, true);
+
+        animator.set_elapsed(1.25);
+        assert_eq!(1.0, animator.ring_alpha(0, 4));
+        assert_eq!(0.25, animator.ring_alpha(1, 4));
+        assert_eq!(0.0, animator.ring_alpha(2, 4));
+
+        // One loop is four rings plus the hold at the end.
+        assert_eq!(7.0, animator.cycle_duration(4));
+        animator.set_elapsed(7.25);
+        assert_eq!(0.25, animator.ring_alpha(0, 4));
+    }
+
+    #[test]
+    fn test_palette_cycle() {
+        let palette = Palette::by_name("holi").unwrap();
+        let mut animator = Animator::new(AnimationMode::PaletteCycle, 1.0, true);
+        let glyph = AlphaToDisplay { alpha: "a".to_string(), x: 0.0, y: 0.0, ring: 0, letter: 0 };
+
+        assert_eq!(palette.stops[0], animator.glyph_color(&palette, ColorMode::Letter, &glyph, 5));
+        animator.update(2.0);
+        assert_eq!(2, animator.palette_shift(5));
+        assert_eq!(palette.stops[3], animator.glyph_color(&palette, ColorMode::Letter, &glyph, 5));
+        animator.update(3.0);
+        assert_eq!(0, animator.palette_shift(5));
+    }
+
+    #[test]
+    fn test_palette_cycle_direction() {
+        // Colors move outwards: one step on, every ring has the color of the
+        // ring inside it, and the center that of the outer ring.
+
+        let palette = Palette::by_name("marigold").unwrap();
+        let mut animator = Animator::new(AnimationMode::PaletteCycle, 1.0, true);
+        let before = (0..4).map(|ring| animator.color_at(&palette, ColorMode::Ring, ring, ring, 4)).collect::<Vec<Rgba>>();
+
+        animator.update(1.0);
+        let after = (0..4).map(|ring| animator.color_at(&palette, ColorMode::Ring, ring, ring, 4)).collect::<Vec<Rgba>>();
+
+        assert_ne!(before[0], before[1]);
+        assert_eq!(before[..3], after[1..]);
+        assert_eq!(before[3], after[0]);
+
+        // Letters move the same way: the second letter has the first color.
+        let holi = Palette::by_name("holi").unwrap();
+        assert_eq!(holi.stops[0], animator.color_at(&holi, ColorMode::Letter, 0, 1, 4));
+    }
+}
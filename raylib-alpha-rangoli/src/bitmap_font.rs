@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::{fs::File, io, path::Path};
+
+use crate::metrics::FontMetrics;
+
+// Raylib's image fonts (XNA style) start at the space character and separate
+// glyphs with magenta key-colored borders. See rtext.c LoadFontFromImage().
+pub const FIRST_CHAR: u32 = 32;
+const KEY_COLOR: [u8; 4] = [255, 0, 255, 255];
+const BLANK: [u8; 4] = [0, 0, 0, 0];
+const FALLBACK_CHAR: char = '?';
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// A raylib image font decoded on the CPU, so glyphs can be measured and
+/// rasterized without a window or a GPU.
+pub struct BitmapFont {
+    width: usize,
+    height: usize,
+    // RGBA pixels, with the key color already replaced by transparency.
+    pixels: Vec<[u8; 4]>,
+    base_size: usize,
+    glyphs: HashMap<char, GlyphRect>,
+}
+
+impl BitmapFont {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+        let mut reader = decoder.read_info().map_err(invalid_data)?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut buf).map_err(invalid_data)?;
+
+        let pixels = buf[..frame.buffer_size()]
+            .chunks(frame.color_type.samples())
+            .map(|px| match px {
+                [r, g, b, a] => [*r, *g, *b, *a],
+                [r, g, b] => [*r, *g, *b, 255],
+                [l, a] => [*l, *l, *l, *a],
+                [l] => [*l, *l, *l, 255],
+                _ => BLANK,
+            })
+            .collect::<Vec<[u8; 4]>>();
+
+        Self::from_rgba(frame.width as usize, frame.height as usize, pixels).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "no key-color glyph grid found in image")
+        })
+    }
+
+    /// Scans the glyph grid the same way raylib does: the key-colored margin
+    /// in the top-left corner gives the glyph and line spacing, then every
+    /// line is walked left to right collecting glyph rectangles.
+    pub fn from_rgba(width: usize, height: usize, mut pixels: Vec<[u8; 4]>) -> Option<Self> {
+        if pixels.len() != width * height {
+            return None;
+        }
+        let is_key = |pixels: &Vec<[u8; 4]>, x: usize, y: usize| {
+            x >= width || y >= height || pixels[y * width + x] == KEY_COLOR
+        };
+
+        let first = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .find(|&(x, y)| !is_key(&pixels, x, y));
+        let (char_spacing, line_spacing) = match first {
+            Some((x, y)) if x > 0 && y > 0 => (x, y),
+            _ => return None,
+        };
+
+        let mut char_height = 0;
+        while !is_key(&pixels, char_spacing, line_spacing + char_height) {
+            char_height += 1;
+        }
+
+        let mut glyphs = HashMap::new();
+        let mut index = 0;
+        let mut line_y = line_spacing;
+
+        while line_y < height {
+            let mut x = char_spacing;
+            while !is_key(&pixels, x, line_y) {
+                let mut char_width = 0;
+                while !is_key(&pixels, x + char_width, line_y) {
+                    char_width += 1;
+                }
+                if let Some(c) = char::from_u32(FIRST_CHAR + index) {
+                    glyphs.insert(c, GlyphRect { x, y: line_y, width: char_width, height: char_height });
+                }
+                index += 1;
+                x += char_width + char_spacing;
+            }
+            line_y += char_height + line_spacing;
+        }
+
+        for px in pixels.iter_mut() {
+            if *px == KEY_COLOR {
+                *px = BLANK;
+            }
+        }
+
+        Some(Self {
+            width,
+            height,
+            pixels,
+            base_size: char_height,
+            glyphs,
+        })
+    }
+
+    pub fn base_size(&self) -> usize {
+        self.base_size
+    }
+
+    /// Like raylib, characters missing from the font are drawn as '?'.
+    pub fn glyph(&self, c: char) -> Option<&GlyphRect> {
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&FALLBACK_CHAR))
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x]
+        } else {
+            BLANK
+        }
+    }
+}
+
+// Image fonts have no advances, so every glyph advances by its own width.
+
+impl FontMetrics for BitmapFont {
+    fn base_size(&self) -> f32 {
+        self.base_size as f32
+    }
+
+    fn has_glyph(&self, c: char) -> bool {
+        self.glyphs.contains_key(&c)
+    }
+
+    fn advance(&self, c: char) -> f32 {
+        self.glyph(c).map_or(0.0, |rect| rect.width as f32)
+    }
+
+    // The columns of the glyph rectangle holding any visible pixel, as glyphs
+    // may leave blank columns on either side of their strokes.
+    fn ink_bounds(&self, c: char) -> Option<(f32, f32)> {
+        let rect = self.glyph(c)?;
+        let is_inked = |x: usize| (rect.y..rect.y + rect.height).any(|y| self.pixel(rect.x + x, y)[3] > 0);
+
+        let left = (0..rect.width).find(|&x| is_inked(x))?;
+        let right = (0..rect.width).rev().find(|&x| is_inked(x))?;
+        Some((left as f32, (right + 1) as f32))
+    }
+}
+
+fn invalid_data<E: std::error::Error + Send + Sync + 'static>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const K: [u8; 4] = KEY_COLOR;
+    const W: [u8; 4] = [255, 255, 255, 255];
+
+    #[test]
+    fn test_glyph_grid() {
+        // Two glyphs, 2x2 and 1x2, on a one pixel key-colored margin.
+        #[rustfmt::skip]
+        let pixels = vec![
+            K, K, K, K, K, K,
+            K, W, W, K, W, K,
+            K, W, BLANK, K, W, K,
+            K, K, K, K, K, K,
+        ];
+        let font = BitmapFont::from_rgba(6, 4, pixels).unwrap();
+
+        assert_eq!(2, font.base_size());
+        assert_eq!(Some(&GlyphRect { x: 1, y: 1, width: 2, height: 2 }), font.glyph(' '));
+        assert_eq!(Some(&GlyphRect { x: 4, y: 1, width: 1, height: 2 }), font.glyph('!'));
+        assert!(!font.has_glyph('?'));
+        assert_eq!(None, font.glyph('a'));
+        assert_eq!(BLANK, font.pixel(0, 0));
+        assert_eq!(7.0, font.measure_text(" !", 4.0, 1.0));
+        assert_eq!(Some((0.0, 2.0)), font.ink_bounds(' '));
+        assert_eq!((0.0, 4.0), font.ink_extent(" !", 2.0, 1.0));
+    }
+
+    #[test]
+    fn test_bundled_font() {
+        let font = BitmapFont::load(crate::fonts::bundled_font_path("alagard")).unwrap();
+        assert!(font.has_glyph('a'));
+        assert!(font.has_glyph('Z'));
+        assert!(!font.has_glyph('α'));
+        assert_eq!(font.glyph('?'), font.glyph('α'));
+
+        // The bundled glyphs are cropped to their strokes, and the space is blank.
+        assert_eq!(Some((0.0, font.advance('i'))), font.ink_bounds('i'));
+        assert_eq!(None, font.ink_bounds(' '));
+    }
+}
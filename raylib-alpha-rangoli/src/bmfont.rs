@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::{fs, io, path::Path};
+
+use crate::metrics::FontMetrics;
+
+const FALLBACK_CHAR: char = '?';
+
+/// One `char` line of a BMFont descriptor. Offsets and advance are in pixels
+/// at the size the font was generated at.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BmGlyph {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub x_offset: i32,
+    pub y_offset: i32,
+    pub x_advance: i32,
+    pub page: usize,
+}
+
+/// An AngelCode BMFont read from its text (.fnt) descriptor. The descriptor
+/// alone has every measurement, so the PNG pages it refers to are only
+/// listed, never decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BmFont {
+    line_height: usize,
+    pages: Vec<String>,
+    glyphs: HashMap<char, BmGlyph>,
+}
+
+impl BmFont {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a text BMFont descriptor"))
+    }
+
+    /// Reads the `common`, `page` and `char` lines of a descriptor, ignoring
+    /// the rest. Fails without a line height or without any glyph.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut line_height = 0;
+        let mut pages = Vec::new();
+        let mut glyphs = HashMap::new();
+
+        for line in text.lines() {
+            let (tag, attrs) = match line.trim().split_once(char::is_whitespace) {
+                Some((tag, rest)) => (tag, parse_attributes(rest)),
+                None => continue,
+            };
+            let int = |key: &str| attrs.get(key).and_then(|value| value.parse::<i32>().ok());
+
+            match tag {
+                "common" => line_height = int("lineHeight")?.max(0) as usize,
+                "page" => pages.push(attrs.get("file")?.clone()),
+                "char" => {
+                    let c = char::from_u32(int("id")? as u32)?;
+                    glyphs.insert(
+                        c,
+                        BmGlyph {
+                            x: int("x").unwrap_or(0).max(0) as usize,
+                            y: int("y").unwrap_or(0).max(0) as usize,
+                            width: int("width").unwrap_or(0).max(0) as usize,
+                            height: int("height").unwrap_or(0).max(0) as usize,
+                            x_offset: int("xoffset").unwrap_or(0),
+                            y_offset: int("yoffset").unwrap_or(0),
+                            x_advance: int("xadvance").unwrap_or(0),
+                            page: int("page").unwrap_or(0).max(0) as usize,
+                        },
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        if line_height == 0 || glyphs.is_empty() {
+            return None;
+        }
+        Some(Self { line_height, pages, glyphs })
+    }
+
+    /// Raylib's LoadBMFont() uses the line height as the base size.
+    pub fn line_height(&self) -> usize {
+        self.line_height
+    }
+
+    /// File names of the PNG pages, relative to the descriptor.
+    pub fn pages(&self) -> &[String] {
+        &self.pages
+    }
+
+    /// Like raylib, characters missing from the font are drawn as '?'.
+    pub fn glyph(&self, c: char) -> Option<&BmGlyph> {
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&FALLBACK_CHAR))
+    }
+}
+
+// Raylib falls back on the glyph width and offset when a glyph has no advance.
+// The glyph rectangles of a descriptor are cropped to the ink.
+
+impl FontMetrics for BmFont {
+    fn base_size(&self) -> f32 {
+        self.line_height as f32
+    }
+
+    fn has_glyph(&self, c: char) -> bool {
+        self.glyphs.contains_key(&c)
+    }
+
+    fn advance(&self, c: char) -> f32 {
+        match self.glyph(c) {
+            Some(glyph) if glyph.x_advance != 0 => glyph.x_advance as f32,
+            Some(glyph) => (glyph.width as i32 + glyph.x_offset) as f32,
+            None => 0.0,
+        }
+    }
+
+    fn ink_bounds(&self, c: char) -> Option<(f32, f32)> {
+        match self.glyph(c) {
+            Some(glyph) if glyph.width > 0 => Some((glyph.x_offset as f32, (glyph.x_offset + glyph.width as i32) as f32)),
+            _ => None,
+        }
+    }
+}
+
+// Splits `key=value key="quoted value"` pairs.
+
+fn parse_attributes(text: &str) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    let mut rest = text.trim_start();
+
+    while let Some((key, tail)) = rest.split_once('=') {
+        let (value, tail) = match tail.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => tail.split_once(char::is_whitespace).unwrap_or((tail, "")),
+        };
+        attrs.insert(key.trim().to_string(), value.to_string());
+        rest = tail.trim_start();
+    }
+
+    attrs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESCRIPTOR: &str = r#"info face="Pixel Sans" size=16 bold=0 italic=0
+common lineHeight=20 base=16 scaleW=128 scaleH=128 pages=1 packed=0
+page id=0 file="pixel sans_0.png"
+chars count=3
+char id=63   x=0   y=0   width=6   height=12  xoffset=0   yoffset=4   xadvance=7   page=0  chnl=15
+char id=105  x=8   y=0   width=2   height=12  xoffset=1   yoffset=4   xadvance=4   page=0  chnl=15
+char id=109  x=12  y=0   width=9   height=9   xoffset=1   yoffset=7   xadvance=0   page=0  chnl=15
+"#;
+
+    #[test]
+    fn test_parse() {
+        let font = BmFont::parse(DESCRIPTOR).unwrap();
+
+        assert_eq!(20, font.line_height());
+        assert_eq!(["pixel sans_0.png"], font.pages());
+        assert_eq!(
+            Some(&BmGlyph { x: 8, y: 0, width: 2, height: 12, x_offset: 1, y_offset: 4, x_advance: 4, page: 0 }),
+            font.glyph('i')
+        );
+        assert!(font.has_glyph('m'));
+        assert!(!font.has_glyph('α'));
+        assert_eq!(font.glyph('?'), font.glyph('α'));
+
+        assert_eq!(None, BmFont::parse("info face=\"Empty\" size=16\n"));
+        assert_eq!(None, BmFont::parse(&DESCRIPTOR.replace("lineHeight=20", "")));
+    }
+
+    #[test]
+    fn test_measure_text() {
+        let font = BmFont::parse(DESCRIPTOR).unwrap();
+
+        // 'm' has no advance, so it takes its width plus offset.
+        assert_eq!(4.0, font.advance('i'));
+        assert_eq!(10.0, font.advance('m'));
+        assert_eq!(7.0, font.advance('α'));
+        assert_eq!(29.0, font.measure_text("mi", 40.0, 1.0));
+        assert_eq!(Some((1.0, 3.0)), font.ink_bounds('i'));
+        assert_eq!((2.0, 27.0), font.ink_extent("mi", 40.0, 1.0));
+    }
+}
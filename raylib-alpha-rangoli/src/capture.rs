@@ -0,0 +1,235 @@
+use std::io::{self, Write};
+
+use crate::animation::Animator;
+use crate::layout::{self, AlphaToDisplay};
+use crate::palette::{ColorMode, Palette};
+use crate::raster::{self, Framebuffer, RasterFont};
+
+// Trade-off between color quantization quality and encoding time, 1 to 30.
+const GIF_QUANTIZE_SPEED: i32 = 10;
+
+/// The frames of an animation, rendered off-screen on the CPU one at a time
+/// and sampled at the given frame rate for the given duration in seconds.
+/// Every frame is drawn into the same framebuffer, so a capture holds a
+/// single frame in memory however long it runs.
+pub struct Capture<'a, F: RasterFont + ?Sized> {
+    rangoli_disp: &'a [Vec<AlphaToDisplay>],
+    font: &'a F,
+    font_size: f32,
+    palette: &'a Palette,
+    color_mode: ColorMode,
+    animator: Animator,
+    fps: u32,
+    frame_count: usize,
+    framebuffer: Framebuffer,
+}
+
+impl<'a, F: RasterFont + ?Sized> Capture<'a, F> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        rangoli_disp: &'a [Vec<AlphaToDisplay>],
+        font: &'a F,
+        font_size: f32,
+        width: usize,
+        height: usize,
+        palette: &'a Palette,
+        color_mode: ColorMode,
+        animator: &Animator,
+        fps: u32,
+        duration: f32,
+    ) -> Self {
+        let fps = fps.max(1);
+
+        Self {
+            rangoli_disp,
+            font,
+            font_size,
+            palette,
+            color_mode,
+            animator: *animator,
+            fps,
+            frame_count: ((duration * fps as f32).round() as usize).max(1),
+            framebuffer: Framebuffer::new(width, height, palette.background),
+        }
+    }
+
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    pub fn width(&self) -> usize {
+        self.framebuffer.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.framebuffer.height()
+    }
+
+    /// Draws the given frame over the one drawn before, and returns it.
+    pub fn render_frame(&mut self, frame: usize) -> &Framebuffer {
+        self.animator.set_elapsed(frame as f32 / self.fps as f32);
+        raster::rasterize_into(
+            &mut self.framebuffer,
+            self.rangoli_disp,
+            self.font,
+            self.font_size,
+            self.palette,
+            self.color_mode,
+            &self.animator,
+        );
+        &self.framebuffer
+    }
+}
+
+/// Length of a capture that plays the animation through exactly once,
+/// or a single frame for a static pattern.
+pub fn default_duration(rangoli_disp: &[Vec<AlphaToDisplay>], animator: &Animator, fps: u32) -> f32 {
+    let cycle = animator.cycle_duration(layout::ring_count(rangoli_disp));
+    if cycle > 0.0 {
+        cycle
+    } else {
+        1.0 / fps.max(1) as f32
+    }
+}
+
+/// Encodes the capture as an animated GIF, each frame as soon as it is
+/// rendered.
+pub fn write_gif<W: Write, F: RasterFont + ?Sized>(mut writer: W, capture: &mut Capture<F>, looping: bool) -> io::Result<()> {
+    let (width, height) = (capture.width() as u16, capture.height() as u16);
+
+    let mut encoder = gif::Encoder::new(&mut writer, width, height, &[]).map_err(to_io_error)?;
+    encoder
+        .set_repeat(if looping { gif::Repeat::Infinite } else { gif::Repeat::Finite(0) })
+        .map_err(to_io_error)?;
+
+    // GIF frame delays are counted in hundredths of a second.
+    let delay = (100.0 / capture.fps() as f32).round() as u16;
+
+    // Quantizing works in place, on a copy of the frame reused throughout.
+    let mut pixels = Vec::new();
+
+    for frame in 0..capture.frame_count() {
+        pixels.clear();
+        pixels.extend_from_slice(capture.render_frame(frame).pixels());
+        let mut gif_frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, GIF_QUANTIZE_SPEED);
+        gif_frame.delay = delay;
+        encoder.write_frame(&gif_frame).map_err(to_io_error)?;
+    }
+
+    // Writes the trailer, which dropping the encoder would do silently.
+    encoder.into_inner()?;
+    writer.flush()
+}
+
+/// Encodes the capture as an animated PNG, which keeps full 24-bit color
+/// unlike GIF, each frame as soon as it is rendered.
+pub fn write_apng<W: Write, F: RasterFont + ?Sized>(mut writer: W, capture: &mut Capture<F>, looping: bool) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(&mut writer, capture.width() as u32, capture.height() as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    // Zero plays means repeat forever.
+    encoder
+        .set_animated(capture.frame_count() as u32, if looping { 0 } else { 1 })
+        .map_err(raster::to_io_error)?;
+    encoder.set_frame_delay(1, capture.fps() as u16).map_err(raster::to_io_error)?;
+
+    let mut png_writer = encoder.write_header().map_err(raster::to_io_error)?;
+    for frame in 0..capture.frame_count() {
+        png_writer.write_image_data(capture.render_frame(frame).pixels()).map_err(raster::to_io_error)?;
+    }
+    png_writer.finish().map_err(raster::to_io_error)?;
+    writer.flush()
+}
+
+fn to_io_error(e: gif::EncodingError) -> io::Error {
+    match e {
+        gif::EncodingError::Io(e) => e,
+        e => io::Error::other(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::AnimationMode;
+    use crate::bitmap_font::BitmapFont;
+    use crate::fonts;
+    use crate::layout::Spacing;
+    use crate::rangoli::{AlphabetSet, RangoliTextPattern};
+
+    #[test]
+    fn test_capture_reveal() {
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+        let rtp_03 = RangoliTextPattern::new(3, &alphabet_set).unwrap();
+        let font = BitmapFont::load(fonts::bundled_font_path(fonts::DEFAULT_FONT)).unwrap();
+        let glyph_layout = layout::layout_rangoli(&rtp_03, &font, Spacing::Grid, None, 64.0, 48.0);
+        let (fit, rangoli_disp) = (glyph_layout.layout, glyph_layout.glyphs);
+        let palette = Palette::default();
+        let animator = Animator::new(AnimationMode::Reveal, 1.0, false);
+
+        // Three rings plus the hold, at two frames per second.
+        let duration = default_duration(&rangoli_disp, &animator, 2);
+        let mut capture = Capture::new(&rangoli_disp, &font, fit.font_size, 64, 48, &palette, ColorMode::Ring, &animator, 2, duration);
+        assert_eq!(12, capture.frame_count());
+
+        // Rings only ever appear, so the count of glyph pixels never shrinks.
+        // Frames are rendered one after the other into the same framebuffer,
+        // so only their counts are kept.
+        let background = palette.background;
+        let drawn = (0..capture.frame_count())
+            .map(|frame| {
+                let fb = capture.render_frame(frame);
+                fb.pixels().chunks(4).filter(|px| px[..3] != [background.r, background.g, background.b]).count()
+            })
+            .collect::<Vec<usize>>();
+        assert!(drawn[0] > 0);
+        assert!(drawn[0] < drawn[2]);
+        assert!(drawn[2] < drawn[4]);
+        assert_eq!(drawn[6], drawn[11]);
+
+        // Going back to an earlier frame draws it afresh.
+        let first = capture.render_frame(0).pixels().chunks(4).filter(|px| px[..3] != [background.r, background.g, background.b]).count();
+        assert_eq!(drawn[0], first);
+    }
+
+    #[test]
+    fn test_write_streamed() {
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+        let rtp_03 = RangoliTextPattern::new(3, &alphabet_set).unwrap();
+        let font = BitmapFont::load(fonts::bundled_font_path(fonts::DEFAULT_FONT)).unwrap();
+        let glyph_layout = layout::layout_rangoli(&rtp_03, &font, Spacing::Grid, None, 64.0, 48.0);
+        let palette = Palette::default();
+        let animator = Animator::new(AnimationMode::Fade, 1.0, true);
+        let capture = || {
+            Capture::new(&glyph_layout.glyphs, &font, glyph_layout.layout.font_size, 64, 48, &palette, ColorMode::Ring, &animator, 4, 2.0)
+        };
+
+        // Each frame goes to the encoder as soon as it is drawn, and the
+        // files decode back to every one of them.
+
+        let mut gif = Vec::new();
+        write_gif(&mut gif, &mut capture(), true).unwrap();
+        let mut decoder = gif::DecodeOptions::new().read_info(gif.as_slice()).unwrap();
+        let mut gif_frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!((64, 48, 25), (frame.width, frame.height, frame.delay));
+            gif_frames += 1;
+        }
+        assert_eq!(8, gif_frames);
+
+        let mut apng = Vec::new();
+        write_apng(&mut apng, &mut capture(), true).unwrap();
+        let mut reader = png::Decoder::new(apng.as_slice()).read_info().unwrap();
+        assert_eq!(Some(8), reader.info().animation_control().map(|actl| actl.num_frames));
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let mut apng_frames = 0;
+        while reader.next_frame(&mut buffer).is_ok() {
+            apng_frames += 1;
+        }
+        assert_eq!(8, apng_frames);
+    }
+}
@@ -0,0 +1,84 @@
+use std::{error, fmt, io};
+
+/// Everything that can go wrong while generating or rendering a rangoli.
+/// Library code returns these rather than exiting or panicking, and leaves
+/// it to the caller to decide what to do.
+#[derive(Debug)]
+pub enum RangoliError {
+    // The rangoli size n is below the smallest size of one ring.
+    InvalidSize { n: i32, min: i32 },
+    EmptyAlphabet,
+    // An n-ring rangoli needs at least n tokens.
+    AlphabetTooShort { needed: usize, available: usize },
+    // The delimiter must be printable and must not clash with a token.
+    InvalidDelimiter(char),
+    // A token is empty, or holds whitespace or control characters.
+    InvalidToken(String),
+    // A named preset, such as a script or a palette, does not exist.
+    UnknownName { kind: &'static str, name: String, choices: Vec<&'static str> },
+    // Text that does not read back as a rangoli. The row and, where known,
+    // the column, counted in grapheme clusters, are 0-based.
+    SymmetryBreak { row: usize, col: Option<usize>, reason: String },
+    Font(String),
+    Io(io::Error),
+}
+
+impl RangoliError {
+    /// Process exit code for command line front ends: 1 for a bad size,
+    /// 2 for other bad input and 3 for I/O failures.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RangoliError::InvalidSize { .. } | RangoliError::AlphabetTooShort { .. } => 1,
+            RangoliError::Io(_) => 3,
+            _ => 2,
+        }
+    }
+}
+
+impl fmt::Display for RangoliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RangoliError::InvalidSize { n, min } => {
+                write!(f, "Parameter NUMBER should be at least {}\nNUMBER: {}", min, n)
+            }
+            RangoliError::EmptyAlphabet => write!(f, "The alphabet is empty."),
+            RangoliError::AlphabetTooShort { needed, available } => write!(
+                f,
+                "Parameter NUMBER should not exceed the {} tokens of the alphabet\nNUMBER: {}",
+                available, needed
+            ),
+            RangoliError::InvalidDelimiter(c) => {
+                write!(f, "Invalid delimiter {:?}: it must be printable and not one of the tokens.", c)
+            }
+            RangoliError::InvalidToken(token) => {
+                write!(f, "Invalid token {:?}: tokens must be non-empty, without whitespace or control characters.", token)
+            }
+            RangoliError::UnknownName { kind, name, choices } => {
+                write!(f, "Unknown {} '{}'. Choose one of: {}", kind, name, choices.join(", "))
+            }
+            RangoliError::SymmetryBreak { row, col: Some(col), reason } => {
+                write!(f, "Not a rangoli: line {}, column {}: {}.", row + 1, col + 1, reason)
+            }
+            RangoliError::SymmetryBreak { row, col: None, reason } => {
+                write!(f, "Not a rangoli: line {}: {}.", row + 1, reason)
+            }
+            RangoliError::Font(msg) => write!(f, "{}", msg),
+            RangoliError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for RangoliError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            RangoliError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RangoliError {
+    fn from(e: io::Error) -> Self {
+        RangoliError::Io(e)
+    }
+}
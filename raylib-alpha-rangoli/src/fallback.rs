@@ -0,0 +1,141 @@
+use crate::metrics::FontMetrics;
+use crate::palette::Rgba;
+use crate::raster::{Framebuffer, RasterFont};
+
+/// A primary font followed by fallback fonts that fill in the glyphs it lacks.
+/// Each character is measured and drawn with the first font that has it, or
+/// with the primary font, which then draws its '?'.
+pub struct FontChain<F> {
+    fonts: Vec<F>,
+}
+
+impl<F: FontMetrics> FontChain<F> {
+    pub fn new(primary: F, fallbacks: Vec<F>) -> Self {
+        let mut fonts = vec![primary];
+        fonts.extend(fallbacks);
+        Self { fonts }
+    }
+
+    /// The primary font first, then the fallbacks in order.
+    pub fn fonts(&self) -> &[F] {
+        &self.fonts
+    }
+
+    pub fn primary(&self) -> &F {
+        &self.fonts[0]
+    }
+
+    /// Index of the font that draws c.
+    pub fn font_index(&self, c: char) -> usize {
+        self.fonts.iter().position(|font| font.has_glyph(c)).unwrap_or(0)
+    }
+
+    pub fn font_for(&self, c: char) -> &F {
+        &self.fonts[self.font_index(c)]
+    }
+
+    /// Splits text into runs of characters drawn with the same font, each
+    /// with the index of that font.
+    pub fn runs<'t>(&self, text: &'t str) -> Vec<(usize, &'t str)> {
+        let mut runs: Vec<(usize, &'t str)> = Vec::new();
+        let mut start = 0;
+
+        for (i, c) in text.char_indices() {
+            let index = self.font_index(c);
+            match runs.last_mut() {
+                Some((last, run)) if *last == index => *run = &text[start..i + c.len_utf8()],
+                _ => {
+                    start = i;
+                    runs.push((index, &text[i..i + c.len_utf8()]));
+                }
+            }
+        }
+
+        runs
+    }
+}
+
+// Fonts in a chain can have different base sizes. The chain measures at the
+// primary's, so a fallback glyph is scaled to what it measures at that size.
+
+impl<F: FontMetrics> FontMetrics for FontChain<F> {
+    fn base_size(&self) -> f32 {
+        self.primary().base_size()
+    }
+
+    fn has_glyph(&self, c: char) -> bool {
+        self.fonts.iter().any(|font| font.has_glyph(c))
+    }
+
+    fn advance(&self, c: char) -> f32 {
+        let font = self.font_for(c);
+        font.advance(c) * self.base_size() / font.base_size()
+    }
+
+    fn ink_bounds(&self, c: char) -> Option<(f32, f32)> {
+        let font = self.font_for(c);
+        let scale = self.base_size() / font.base_size();
+        font.ink_bounds(c).map(|(left, right)| (left * scale, right * scale))
+    }
+
+    // Each run is measured by its own font, one spacing apart, the way
+    // renderers draw them.
+    fn measure_text(&self, text: &str, font_size: f32, spacing: f32) -> f32 {
+        let runs = self.runs(text);
+        let width: f32 = runs.iter().map(|(index, run)| self.fonts[*index].measure_text(run, font_size, spacing)).sum();
+
+        width + runs.len().saturating_sub(1) as f32 * spacing
+    }
+}
+
+impl<F: RasterFont> RasterFont for FontChain<F> {
+    fn draw_glyph(&self, framebuffer: &mut Framebuffer, c: char, x: f32, y: f32, font_size: f32, tint: Rgba) {
+        self.font_for(c).draw_glyph(framebuffer, c, x, y, font_size, tint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Monospace;
+
+    // Has only the given characters, each one base size wide.
+
+    struct Covers(&'static str, f32);
+
+    impl FontMetrics for Covers {
+        fn base_size(&self) -> f32 {
+            self.1
+        }
+
+        fn has_glyph(&self, c: char) -> bool {
+            self.0.contains(c)
+        }
+
+        fn advance(&self, _: char) -> f32 {
+            self.1
+        }
+    }
+
+    #[test]
+    fn test_font_chain() {
+        let chain = FontChain::new(Covers("ab?", 10.0), vec![Covers("bαβ", 20.0), Covers("γ", 5.0)]);
+
+        assert_eq!(0, chain.font_index('b'));
+        assert_eq!(1, chain.font_index('α'));
+        assert_eq!(2, chain.font_index('γ'));
+        assert_eq!(0, chain.font_index('z'));
+        assert!(chain.has_glyph('γ'));
+        assert!(!chain.has_glyph('z'));
+        assert_eq!(vec![(0, "ab"), (1, "αβ"), (2, "γ"), (0, "a")], chain.runs("abαβγa"));
+        assert!(chain.runs("").is_empty());
+
+        // Every glyph measures one primary base size, whatever font draws it.
+        assert_eq!(10.0, chain.advance('α'));
+        assert_eq!(10.0, chain.advance('γ'));
+        assert_eq!(4.0 * 20.0 + 3.0, chain.measure_text("aαγz", 20.0, 1.0));
+
+        let single = FontChain::new(&Monospace, Vec::new());
+        assert_eq!(Monospace.measure_text("abc", 18.0, 1.0), single.measure_text("abc", 18.0, 1.0));
+    }
+}
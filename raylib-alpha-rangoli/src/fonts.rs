@@ -0,0 +1,188 @@
+use std::path::{Path, PathBuf};
+
+use crate::bitmap_font::BitmapFont;
+use crate::bmfont::BmFont;
+use crate::error::RangoliError;
+use crate::metrics::FontMetrics;
+use crate::raster::RasterFont;
+use crate::rangoli::AlphabetSet;
+use crate::truetype::TrueTypeFont;
+
+pub const RESOURCE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/resources");
+pub const DEFAULT_FONT: &str = "alagard";
+
+// Raylib BMFont images shipped under resources/, by file stem.
+pub const BUNDLED_FONTS: [&str; 8] = [
+    "alagard",
+    "alpha_beta",
+    "jupiter_crash",
+    "mecha",
+    "pixantiqua",
+    "pixelplay",
+    "romulus",
+    "setback",
+];
+
+pub fn bundled_font_path(name: &str) -> PathBuf {
+    Path::new(RESOURCE_DIR).join(format!("{name}.png"))
+}
+
+/// Resolves either the name of a bundled font or a path to a font file.
+/// Fails when neither exists, rather than letting raylib fall back on its
+/// default font without a word.
+pub fn resolve_font(name_or_path: &str) -> Result<PathBuf, RangoliError> {
+    let bundled_name = name_or_path.to_lowercase();
+    if BUNDLED_FONTS.contains(&bundled_name.as_str()) {
+        return Ok(bundled_font_path(&bundled_name));
+    }
+
+    let path = PathBuf::from(name_or_path);
+    if path.is_file() {
+        Ok(path)
+    } else {
+        Err(RangoliError::Font(format!(
+            "Font '{}' is neither a bundled font nor an existing file.\nBundled fonts: {}",
+            name_or_path,
+            BUNDLED_FONTS.join(", ")
+        )))
+    }
+}
+
+/// Raylib image fonts are plain PNG files, which can also be decoded on the CPU.
+pub fn is_bitmap_font(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("png"))
+        .unwrap_or(false)
+}
+
+/// TrueType and OpenType fonts are outlines, rasterized at whatever size
+/// they are loaded at.
+pub fn is_truetype_font(path: &Path) -> bool {
+    matches!(extension(path).as_str(), "ttf" | "otf")
+}
+
+/// Reads the metrics of a font file on the CPU, picking the reader from the
+/// file extension the same way raylib's LoadFont() does: image fonts (.png),
+/// BMFont descriptors (.fnt) and TrueType or OpenType fonts (.ttf, .otf).
+/// TrueType fonts are measured at ttf_size, the size raylib loads them at.
+pub fn load_metrics(path: &Path, ttf_size: usize) -> Result<Box<dyn FontMetrics>, RangoliError> {
+    let font_error = |e: std::io::Error| RangoliError::Font(format!("Couldn't load font {}: {}", path.display(), e));
+
+    match extension(path).as_str() {
+        "png" => Ok(Box::new(BitmapFont::load(path).map_err(font_error)?)),
+        "fnt" => Ok(Box::new(BmFont::load(path).map_err(font_error)?)),
+        "ttf" | "otf" => Ok(Box::new(TrueTypeFont::load(path, ttf_size).map_err(font_error)?)),
+        _ => Err(RangoliError::Font(format!(
+            "Font {} is not a .png, .fnt, .ttf or .otf file",
+            path.display()
+        ))),
+    }
+}
+
+/// Loads a font that can be drawn on the CPU, for PNG, GIF and APNG export.
+/// BMFont descriptors are not supported, as their pages are never decoded.
+pub fn load_raster_font(path: &Path, ttf_size: usize) -> Result<Box<dyn RasterFont>, RangoliError> {
+    let font_error = |e: std::io::Error| RangoliError::Font(format!("Couldn't load font {}: {}", path.display(), e));
+
+    match extension(path).as_str() {
+        "png" => Ok(Box::new(BitmapFont::load(path).map_err(font_error)?)),
+        "ttf" | "otf" => Ok(Box::new(TrueTypeFont::load(path, ttf_size).map_err(font_error)?)),
+        _ => Err(RangoliError::Font(format!(
+            "Font {} can't be drawn on the CPU, only .png, .ttf and .otf fonts can",
+            path.display()
+        ))),
+    }
+}
+
+/// The characters to load from a TrueType font for the given alphabets:
+/// printable ASCII, which the status line and the '?' fallback need, and
+/// every character of the alphabets in both cases, which the case key
+/// switches between. Raylib only loads glyphs it is asked for.
+pub fn codepoints(alphabet_sets: &[&AlphabetSet]) -> String {
+    let mut chars: Vec<char> = (' '..='~').collect();
+
+    for alphabet_set in alphabet_sets {
+        for alphabet_set in [(*alphabet_set).clone(), alphabet_set.to_uppercase(), alphabet_set.to_lowercase()] {
+            for c in alphabet_set.get_alphabet().iter().flat_map(|token| token.chars()) {
+                if !chars.contains(&c) {
+                    chars.push(c);
+                }
+            }
+        }
+    }
+
+    chars.into_iter().collect()
+}
+
+/// Characters of the alphabet tokens that have no glyph in the font, each
+/// listed once. Raylib draws these as '?' instead.
+pub fn missing_glyphs<M: FontMetrics + ?Sized>(font: &M, alphabet_set: &AlphabetSet) -> Vec<char> {
+    let mut missing: Vec<char> = Vec::new();
+
+    for c in alphabet_set.get_alphabet().iter().flat_map(|token| token.chars()) {
+        if !font.has_glyph(c) && !missing.contains(&c) {
+            missing.push(c);
+        }
+    }
+
+    missing
+}
+
+fn extension(path: &Path) -> String {
+    path.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::truetype::TTF_BASE_SIZE;
+
+    #[test]
+    fn test_resolve_font() {
+        for name in BUNDLED_FONTS {
+            assert!(resolve_font(name).unwrap().is_file());
+        }
+        assert_eq!(resolve_font("Mecha").unwrap(), bundled_font_path("mecha"));
+        assert!(matches!(resolve_font("resources/bogus_font.png"), Err(RangoliError::Font(_))));
+    }
+
+    #[test]
+    fn test_missing_glyphs() {
+        let font = BitmapFont::load(bundled_font_path(DEFAULT_FONT)).unwrap();
+
+        assert!(missing_glyphs(&font, &AlphabetSet::new('a', 'z').unwrap()).is_empty());
+        assert_eq!(vec!['α', 'β'], missing_glyphs(&font, &AlphabetSet::from_token_str("a α b β").unwrap()));
+    }
+
+    #[test]
+    fn test_load_metrics() {
+        let path = bundled_font_path(DEFAULT_FONT);
+        let metrics = load_metrics(&path, TTF_BASE_SIZE).unwrap();
+        let font = BitmapFont::load(&path).unwrap();
+
+        assert_eq!(font.measure_text("rangoli", 18.0, 1.0), metrics.measure_text("rangoli", 18.0, 1.0));
+        assert!(matches!(load_metrics(Path::new("Cargo.toml"), TTF_BASE_SIZE), Err(RangoliError::Font(_))));
+        assert!(matches!(load_metrics(Path::new("resources/bogus_font.fnt"), TTF_BASE_SIZE), Err(RangoliError::Font(_))));
+    }
+
+    #[test]
+    fn test_load_raster_font() {
+        let path = bundled_font_path(DEFAULT_FONT);
+        let font = load_raster_font(&path, TTF_BASE_SIZE).unwrap();
+
+        assert_eq!(load_metrics(&path, TTF_BASE_SIZE).unwrap().measure_text("rangoli", 18.0, 1.0), font.measure_text("rangoli", 18.0, 1.0));
+        assert!(is_truetype_font(Path::new("fonts/NotoSans.TTF")));
+        assert!(!is_truetype_font(&path));
+        assert!(matches!(load_raster_font(Path::new("font.fnt"), TTF_BASE_SIZE), Err(RangoliError::Font(_))));
+    }
+
+    #[test]
+    fn test_codepoints() {
+        let greek = AlphabetSet::from_token_str("α β").unwrap();
+        let codepoints = codepoints(&[&greek, &greek]);
+
+        assert!(codepoints.starts_with(" !\"#"));
+        assert!(codepoints.ends_with("αβΑΒ"));
+        assert_eq!(95 + 4, codepoints.chars().count());
+    }
+}
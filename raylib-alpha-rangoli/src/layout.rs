@@ -0,0 +1,464 @@
+use std::collections::HashMap;
+
+use crate::metrics::FontMetrics;
+use crate::rangoli::{Cell, RangoliGrid, RangoliTextPattern};
+
+pub const SCREEN_WIDTH: i32 = 1280;
+pub const SCREEN_HEIGHT: i32 = 1024;
+// Reference font size. Glyph offsets are measured at this size, and the
+// spacing constants below are tuned for it; Layout scales all of them.
+pub const FONT_SIZE: f32 = 18.0;
+
+// Gap kept on either side of the ink of every token.
+const ALPHA_WIDTH_PAD: f32 = 5.0;
+const ALPHA_HEIGHT_PAD: f32 = 18.5;
+// Smallest gap kept between the pattern and any edge of the screen.
+const MARGIN: f32 = 40.0;
+const MIN_FONT_SIZE: f32 = 4.0;
+
+/// How tokens are spaced along a line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Spacing {
+    // Every token sits in a cell as wide as the widest token, so columns line
+    // up from one line to the next, as on a monospace grid.
+    Grid,
+    // Every token takes only its own width, like proportional type. Lines
+    // stay symmetric, but columns no longer line up.
+    Proportional,
+}
+
+impl Spacing {
+    pub const NAMES: [&'static str; 2] = ["grid", "proportional"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "grid" => Some(Spacing::Grid),
+            "proportional" => Some(Spacing::Proportional),
+            _ => None,
+        }
+    }
+}
+
+/// Where the ink of a token lies, relative to the x-coordinate it is drawn at.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenExtent {
+    pub left: f32,
+    pub right: f32,
+}
+
+impl TokenExtent {
+    pub fn width(&self) -> f32 {
+        self.right - self.left
+    }
+
+    pub fn center(&self) -> f32 {
+        (self.left + self.right) / 2.0
+    }
+}
+
+/// Font size and spacing that make a pattern fit a screen of the given size,
+/// centered both horizontally and vertically.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub screen_width: f32,
+    pub screen_height: f32,
+    pub font_size: f32,
+    // Scale relative to the reference FONT_SIZE.
+    pub scale: f32,
+    // Width of the ink of the widest token, and so of every grid cell.
+    pub cell_width: f32,
+    // Horizontal distance between neighbouring columns of the grid, tokens
+    // and delimiters alike. Neighbouring tokens are two columns apart.
+    pub char_pitch: f32,
+    pub line_pitch: f32,
+    // Where the middle of the pivotal token of every line is drawn.
+    pub center_x: f32,
+    pub top: f32,
+}
+
+impl Layout {
+    /// Picks the largest scale at which the pattern fits within the margins.
+    /// The width of the widest token, measured at the reference FONT_SIZE,
+    /// sets the width of every token cell.
+    pub fn fit(
+        rangoli_text: &RangoliTextPattern,
+        max_token_width: f32,
+        screen_width: f32,
+        screen_height: f32,
+    ) -> Self {
+        let (pattern_width, pattern_height) = pattern_size(rangoli_text, max_token_width);
+        let fit_x = (screen_width - 2.0 * MARGIN).max(0.0) / pattern_width.max(1.0);
+        let fit_y = (screen_height - 2.0 * MARGIN).max(0.0) / pattern_height;
+        let scale = fit_x.min(fit_y).max(MIN_FONT_SIZE / FONT_SIZE);
+
+        Self::at_scale(rangoli_text, max_token_width, scale, screen_width, screen_height)
+    }
+
+    /// Draws the pattern at a fixed font size, centered on the screen whether
+    /// it fits or not.
+    pub fn at_font_size(
+        rangoli_text: &RangoliTextPattern,
+        max_token_width: f32,
+        font_size: f32,
+        screen_width: f32,
+        screen_height: f32,
+    ) -> Self {
+        Self::at_scale(rangoli_text, max_token_width, font_size.max(MIN_FONT_SIZE) / FONT_SIZE, screen_width, screen_height)
+    }
+
+    fn at_scale(
+        rangoli_text: &RangoliTextPattern,
+        max_token_width: f32,
+        scale: f32,
+        screen_width: f32,
+        screen_height: f32,
+    ) -> Self {
+        let (_, pattern_height) = pattern_size(rangoli_text, max_token_width);
+        let char_pitch = (max_token_width / 2.0) + ALPHA_WIDTH_PAD;
+
+        Self {
+            screen_width,
+            screen_height,
+            font_size: FONT_SIZE * scale,
+            scale,
+            cell_width: max_token_width * scale,
+            char_pitch: char_pitch * scale,
+            line_pitch: ALPHA_HEIGHT_PAD * scale,
+            center_x: screen_width / 2.0,
+            top: (screen_height - pattern_height * scale) / 2.0,
+        }
+    }
+}
+
+// Size of the pattern at scale 1.0: from the left edge of the leftmost glyph
+// to the right edge of the rightmost one, and top to bottom. Proportional
+// lines are never wider than grid ones.
+
+fn pattern_size(rangoli_text: &RangoliTextPattern, max_token_width: f32) -> (f32, f32) {
+    let grid = rangoli_text.get_grid();
+    let mid_index = (grid.cols().max(1) as f32 - 1.0) / 2.0;
+    let line_count = grid.rows().max(1) as f32;
+    let char_pitch = (max_token_width / 2.0) + ALPHA_WIDTH_PAD;
+
+    (
+        (2.0 * mid_index * char_pitch) + max_token_width,
+        ((line_count - 1.0) * ALPHA_HEIGHT_PAD) + FONT_SIZE,
+    )
+}
+
+/// A single token of the rangoli pattern and the top-left position to draw it at.
+/// The layout is independent of any rendering backend, so that the raylib
+/// window and the file exporters all place glyphs in exactly the same spots.
+#[derive(Debug, Clone)]
+pub struct AlphaToDisplay {
+    pub alpha: String,
+    pub x: f32,
+    pub y: f32,
+    // Distance from the center of the pattern, 0 for the pivotal token.
+    pub ring: usize,
+    // Index of the glyph's token within the alphabet.
+    pub letter: usize,
+}
+
+/// Output of the layout stage: the layout fitted to a screen and every glyph
+/// positioned on it, row by row. Renderers take it from here.
+#[derive(Debug, Clone)]
+pub struct GlyphLayout {
+    pub layout: Layout,
+    pub glyphs: Vec<Vec<AlphaToDisplay>>,
+}
+
+/// The whole layout stage in one call: measures every token of the pattern
+/// with the given font metrics, fits the pattern to the screen, or draws it
+/// at the given font size, and positions its glyphs so that the ink of each
+/// is centered on its cell. Nothing here draws, so it runs without a window
+/// or a GPU.
+pub fn layout_rangoli<M>(
+    rangoli_text: &RangoliTextPattern,
+    metrics: &M,
+    spacing: Spacing,
+    font_size: Option<f32>,
+    screen_width: f32,
+    screen_height: f32,
+) -> GlyphLayout
+where
+    M: FontMetrics + ?Sized,
+{
+    let tokens = rangoli_text.get_grid().used_tokens();
+    let (max_token_width, _) = measure_tokens(&tokens, metrics, FONT_SIZE);
+    let layout = match font_size {
+        Some(font_size) => Layout::at_font_size(rangoli_text, max_token_width, font_size, screen_width, screen_height),
+        None => Layout::fit(rangoli_text, max_token_width, screen_width, screen_height),
+    };
+
+    // Fonts do not always scale linearly, whole pixels and spacing among
+    // others, so the tokens are measured again at the size they are drawn at.
+
+    let (_, extents) = measure_tokens(&tokens, metrics, layout.font_size);
+    let glyphs = layout_pattern(rangoli_text, &extents, &layout, spacing);
+
+    GlyphLayout { layout, glyphs }
+}
+
+/// Translates the grid of the pattern into rows of glyphs to draw. Extents
+/// are those of the tokens at the layout's font size.
+pub fn layout_pattern(
+    rangoli_text: &RangoliTextPattern,
+    extents: &HashMap<String, TokenExtent>,
+    layout: &Layout,
+    spacing: Spacing,
+) -> Vec<Vec<AlphaToDisplay>> {
+    let grid = rangoli_text.get_grid();
+
+    // The middle token, the first in the alphabet unless the pattern was
+    // transformed, sits in the middle column of the grid. It is the pivot of
+    // the range of tokens to the left and right respectively.
+
+    let center_col = grid.cols() / 2;
+
+    grid.iter_rows()
+        .map(|cells| {
+        let centers = cell_centers(grid, &cells, center_col, extents, layout, spacing);
+
+        // Delimiters and the padding around the rings are not drawn. Every
+        // token is drawn so that the middle of its ink is the middle of its
+        // cell, however wide the token and wherever the font puts the ink.
+
+        cells.iter()
+            .flatten()
+            .filter_map(|cell| grid.token(cell).map(|token| (cell, token)))
+            .map(|(cell, token)| {
+            let extent = extents.get(token).copied().unwrap_or_default();
+
+            AlphaToDisplay {
+                alpha: token.to_string(),
+                x: centers[cell.col] - extent.center(),
+                y: layout.top + (layout.line_pitch * (cell.row as f32)),
+                ring: cell.ring,
+                letter: cell.token,
+            }
+        }).collect::<Vec<AlphaToDisplay>>()
+    }).collect::<Vec<Vec<AlphaToDisplay>>>()
+}
+
+// The x-coordinate of the middle of every cell of a line. Grid columns are
+// evenly spaced. Proportional lines are packed outwards from the center
+// column instead, with the same gap between the ink of neighbouring tokens,
+// and blank token cells as wide as a grid cell.
+
+fn cell_centers(
+    grid: &RangoliGrid,
+    cells: &[Option<Cell>],
+    center_col: usize,
+    extents: &HashMap<String, TokenExtent>,
+    layout: &Layout,
+    spacing: Spacing,
+) -> Vec<f32> {
+    let mut centers = (0..cells.len())
+        .map(|col| layout.center_x + (col as f32 - center_col as f32) * layout.char_pitch)
+        .collect::<Vec<f32>>();
+
+    if spacing == Spacing::Proportional {
+        let gap = 2.0 * layout.char_pitch - layout.cell_width;
+        let width = |col: usize| {
+            cells[col]
+                .as_ref()
+                .and_then(|cell| grid.token(cell))
+                .and_then(|token| extents.get(token))
+                .map_or(layout.cell_width, TokenExtent::width)
+        };
+
+        for col in (center_col + 2..cells.len()).step_by(2) {
+            centers[col] = centers[col - 2] + (width(col - 2) / 2.0) + gap + (width(col) / 2.0);
+        }
+        for col in (0..center_col.saturating_sub(1)).rev().step_by(2) {
+            centers[col] = centers[col + 2] - (width(col + 2) / 2.0) - gap - (width(col) / 2.0);
+        }
+    }
+
+    centers
+}
+
+/// Number of rings in a laid out pattern, the center token included.
+pub fn ring_count(rangoli_disp: &[Vec<AlphaToDisplay>]) -> usize {
+    rangoli_disp.iter().flatten().map(|glyph| glyph.ring + 1).max().unwrap_or(0)
+}
+
+/// Measures the ink of the given tokens at the given font size, along with
+/// the width of the widest. Only the tokens drawn need measuring, see
+/// RangoliGrid::used_tokens().
+pub fn measure_tokens<M>(tokens: &[&str], metrics: &M, font_size: f32) -> (f32, HashMap<String, TokenExtent>)
+where
+    M: FontMetrics + ?Sized,
+{
+    let mut ret_val: HashMap<String, TokenExtent> = HashMap::new();
+    let mut max_token_width: f32 = 0.0;
+
+    for &token in tokens {
+        let (left, right) = metrics.ink_extent(token, font_size, 1.0);
+        let extent = TokenExtent { left, right };
+        ret_val.insert(token.to_string(), extent);
+
+        if extent.width() > max_token_width {
+            max_token_width = extent.width();
+        }
+    }
+
+    (max_token_width, ret_val)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Monospace;
+    use crate::rangoli::AlphabetSet;
+
+    // A font whose 'i' is a thin stroke right of the middle of its advance,
+    // so centering by advance alone would put it off center.
+    struct OffCenter;
+
+    impl FontMetrics for OffCenter {
+        fn base_size(&self) -> f32 {
+            10.0
+        }
+
+        fn has_glyph(&self, _c: char) -> bool {
+            true
+        }
+
+        fn advance(&self, c: char) -> f32 {
+            if c == 'i' { 8.0 } else { 6.0 }
+        }
+
+        fn ink_bounds(&self, c: char) -> Option<(f32, f32)> {
+            if c == 'i' { Some((5.0, 6.0)) } else { Some((0.0, 6.0)) }
+        }
+    }
+
+    // Middle of the ink of every glyph of a laid out pattern.
+    fn ink_centers(rangoli_disp: &[Vec<AlphaToDisplay>], extents: &HashMap<String, TokenExtent>) -> Vec<Vec<f32>> {
+        rangoli_disp.iter()
+            .map(|r_line| r_line.iter().map(|g| g.x + extents[&g.alpha].center()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_layout_fit() {
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+
+        for (n, width, height) in [(1, 1280.0, 1024.0), (5, 640.0, 480.0), (26, 1280.0, 1024.0), (26, 300.0, 900.0)] {
+            let rtp = RangoliTextPattern::new(n, &alphabet_set).unwrap();
+            let tokens = rtp.get_grid().used_tokens();
+            let (max_token_width, _) = measure_tokens(&tokens, &Monospace, FONT_SIZE);
+            let layout = Layout::fit(&rtp, max_token_width, width, height);
+            let (_, extents) = measure_tokens(&tokens, &Monospace, layout.font_size);
+            let rangoli_disp = layout_pattern(&rtp, &extents, &layout, Spacing::Grid);
+
+            let left = rangoli_disp.iter().flatten().map(|g| g.x + extents[&g.alpha].left).fold(f32::MAX, f32::min);
+            let right = rangoli_disp.iter().flatten().map(|g| g.x + extents[&g.alpha].right).fold(f32::MIN, f32::max);
+            let top = rangoli_disp[0][0].y;
+            let bottom = rangoli_disp[rangoli_disp.len() - 1][0].y + layout.font_size;
+
+            assert!(left >= 0.0 && right <= width, "n={n}: {left}..{right} exceeds {width}");
+            assert!(top >= 0.0 && bottom <= height, "n={n}: {top}..{bottom} exceeds {height}");
+            assert!((left + right - width).abs() < 1.0, "n={n}: not centered horizontally");
+            assert!((top + bottom - height).abs() < 1.0, "n={n}: not centered vertically");
+        }
+    }
+
+    #[test]
+    fn test_layout_font_size() {
+        // A fixed font size is kept as is, centered even when it overflows.
+
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+        let rtp = RangoliTextPattern::new(5, &alphabet_set).unwrap();
+
+        for font_size in [9.0, 36.0, 400.0] {
+            let glyph_layout = layout_rangoli(&rtp, &Monospace, Spacing::Grid, Some(font_size), 640.0, 480.0);
+            let layout = &glyph_layout.layout;
+            let top = glyph_layout.glyphs[0][0].y;
+            let bottom = glyph_layout.glyphs[glyph_layout.glyphs.len() - 1][0].y + layout.font_size;
+
+            assert_eq!(font_size, layout.font_size);
+            assert_eq!(font_size / FONT_SIZE, layout.scale);
+            assert!((top + bottom - 480.0).abs() < 1.0);
+        }
+
+        let fitted = layout_rangoli(&rtp, &Monospace, Spacing::Grid, None, 640.0, 480.0);
+        let fixed = layout_rangoli(&rtp, &Monospace, Spacing::Grid, Some(fitted.layout.font_size), 640.0, 480.0);
+        assert_eq!(fitted.glyphs.len(), fixed.glyphs.len());
+        assert!(fitted.glyphs.iter().flatten().zip(fixed.glyphs.iter().flatten()).all(|(a, b)| (a.x - b.x).abs() < 0.01));
+    }
+
+    #[test]
+    fn test_layout_symmetry() {
+        // Tokens of different widths are each centered on their column, so
+        // every line mirrors around the center of the screen.
+
+        let alphabet_set = AlphabetSet::from_token_str("i mmm ww").unwrap();
+        let rtp = RangoliTextPattern::new(3, &alphabet_set).unwrap();
+
+        for spacing in [Spacing::Grid, Spacing::Proportional] {
+            let glyph_layout = layout_rangoli(&rtp, &Monospace, spacing, None, 1280.0, 1024.0);
+            let (_, extents) = measure_tokens(&rtp.get_grid().used_tokens(), &Monospace, glyph_layout.layout.font_size);
+
+            for centers in ink_centers(&glyph_layout.glyphs, &extents) {
+                for (left, right) in centers.iter().zip(centers.iter().rev()) {
+                    assert!((left + right - 2.0 * glyph_layout.layout.center_x).abs() < 0.01);
+                }
+            }
+            assert_eq!("i", glyph_layout.glyphs[2][2].alpha);
+            assert_eq!("mmm", glyph_layout.glyphs[2][1].alpha);
+        }
+
+        // Tokens past the last ring are not drawn, so they do not narrow the
+        // pattern to make room.
+
+        let rtp_02 = RangoliTextPattern::new(2, &AlphabetSet::from_token_str("i mm wwwwww").unwrap()).unwrap();
+        let used = RangoliTextPattern::new(2, &AlphabetSet::from_token_str("i mm").unwrap()).unwrap();
+        assert_eq!(vec!["i", "mm"], rtp_02.get_grid().used_tokens());
+        assert_eq!(
+            layout_rangoli(&used, &Monospace, Spacing::Grid, None, 1280.0, 1024.0).layout.font_size,
+            layout_rangoli(&rtp_02, &Monospace, Spacing::Grid, None, 1280.0, 1024.0).layout.font_size
+        );
+    }
+
+    #[test]
+    fn test_ink_centering() {
+        // On the grid, the ink of every token is centered on its column, even
+        // where the font draws it off the middle of its advance.
+
+        let alphabet_set = AlphabetSet::from_token_str("i mm").unwrap();
+        let rtp = RangoliTextPattern::new(2, &alphabet_set).unwrap();
+        let glyph_layout = layout_rangoli(&rtp, &OffCenter, Spacing::Grid, None, 1280.0, 1024.0);
+        let layout = glyph_layout.layout;
+        let (_, extents) = measure_tokens(&rtp.get_grid().used_tokens(), &OffCenter, layout.font_size);
+        let centers = ink_centers(&glyph_layout.glyphs, &extents);
+
+        for center in [centers[0][0], centers[1][1], centers[2][0]] {
+            assert!((center - layout.center_x).abs() < 0.01);
+        }
+        assert!((centers[1][2] - centers[1][1] - 2.0 * layout.char_pitch).abs() < 0.01);
+
+        // Proportional lines keep the same gap between the ink of neighbouring
+        // tokens, so the narrow 'i' pulls its neighbours in.
+
+        let glyph_layout = layout_rangoli(&rtp, &OffCenter, Spacing::Proportional, None, 1280.0, 1024.0);
+        let layout = glyph_layout.layout;
+        let middle = &glyph_layout.glyphs[1];
+        let gap = |a: &AlphaToDisplay, b: &AlphaToDisplay| (b.x + extents[&b.alpha].left) - (a.x + extents[&a.alpha].right);
+
+        assert!((gap(&middle[0], &middle[1]) - gap(&middle[1], &middle[2])).abs() < 0.01);
+        assert!((gap(&middle[0], &middle[1]) - (2.0 * layout.char_pitch - layout.cell_width)).abs() < 0.01);
+        assert!(ink_centers(&glyph_layout.glyphs, &extents)[1][2] - layout.center_x < 2.0 * layout.char_pitch);
+    }
+
+    #[test]
+    fn test_spacing_from_name() {
+        for name in Spacing::NAMES {
+            assert!(Spacing::from_name(name).is_some());
+        }
+        assert_eq!(Some(Spacing::Proportional), Spacing::from_name("Proportional"));
+        assert_eq!(None, Spacing::from_name("kerned"));
+    }
+}
@@ -0,0 +1,75 @@
+//! Alphabet rangoli patterns, from text to glyphs.
+//!
+//! The core of the crate needs neither raylib nor clap: build an
+//! [`AlphabetSet`], generate a [`RangoliTextPattern`] from it, and lay the
+//! glyphs out on a screen of any size with [`layout::layout_rangoli`], which
+//! measures them through [`FontMetrics`], of one font or of a [`FontChain`].
+//! The result can be handed to any [`RangoliRenderer`]: printed as plain or
+//! colored text, written to SVG or rasterized to PNG, GIF and APNG on the CPU.
+//!
+//! ```
+//! use raylib_alpha_rangoli::{AlphabetSet, RangoliTextPattern};
+//!
+//! let alphabet_set = AlphabetSet::new('a', 'z')?;
+//! let rangoli = RangoliTextPattern::new(2, &alphabet_set)?;
+//!
+//! assert_eq!(rangoli.padded_lines('-'), vec!["--b--", "b-a-b", "--b--"]);
+//! # Ok::<(), raylib_alpha_rangoli::RangoliError>(())
+//! ```
+//!
+//! Cargo features:
+//!
+//! * `raylib` (default) adds [`raylib_mod`], the interactive Raylib window.
+//! * `cli` (default) builds the `raylib-alpha-rangoli` command line binary.
+//!
+//! Depend on the crate with `default-features = false` to leave both out.
+
+/// Ring reveal, fade and palette cycle animations.
+pub mod animation;
+/// Raylib image fonts, decoded on the CPU.
+pub mod bitmap_font;
+/// BMFont descriptors, read on the CPU.
+pub mod bmfont;
+/// Off-screen frame capture and animated GIF and APNG export.
+pub mod capture;
+/// The error type shared by the whole crate.
+pub mod error;
+/// Fallback chains that fill in the glyphs a font lacks.
+pub mod fallback;
+/// Bundled fonts and font lookup.
+pub mod fonts;
+/// Fitting the pattern to a screen and placing each glyph.
+pub mod layout;
+/// Measuring text with any font, with or without a window.
+pub mod metrics;
+/// Colors, palettes and color modes.
+pub mod palette;
+/// Reading rangoli text back into a pattern.
+pub mod parser;
+/// Alphabets and the text form of the rangoli pattern.
+pub mod rangoli;
+/// CPU framebuffer and PNG export.
+pub mod raster;
+/// The renderer trait shared by every output backend.
+pub mod render;
+/// Interactive Raylib window.
+#[cfg(feature = "raylib")]
+pub mod raylib_mod;
+/// Standalone SVG export.
+pub mod svg_mod;
+/// Plain and ANSI colored text output to a terminal.
+pub mod terminal;
+/// TrueType and OpenType fonts, read on the CPU.
+pub mod truetype;
+/// Inverting, rotating and remapping patterns.
+pub mod transform;
+
+pub use error::RangoliError;
+pub use fallback::FontChain;
+pub use layout::{AlphaToDisplay, GlyphLayout, Layout, Spacing};
+pub use metrics::FontMetrics;
+pub use palette::{ColorMode, Palette, Rgba};
+pub use parser::{parse_rangoli, ParsedRangoli};
+pub use rangoli::{write_rangoli, AlphabetSet, Cell, Cells, Lines, PatternShape, RangoliGrid, RangoliTextPattern, Script};
+pub use render::{RangoliRenderer, Scene};
+pub use transform::Transform;
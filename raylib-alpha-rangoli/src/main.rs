@@ -1,69 +1,533 @@
-#![allow(warnings)]
-
 use clap::Parser;
+use std::fs;
+
+// The binary is a thin command line front end. Everything else lives in the
+// raylib_alpha_rangoli library, see lib.rs.
 
-pub mod rangoli;
-pub mod raylib_mod;
+use raylib_alpha_rangoli::animation::{self, AnimationMode, Animator};
+use raylib_alpha_rangoli::error::RangoliError;
+use raylib_alpha_rangoli::fonts;
+use raylib_alpha_rangoli::layout::Spacing;
+use raylib_alpha_rangoli::metrics::FontMetrics;
+use raylib_alpha_rangoli::palette::{ColorMode, Palette};
+use raylib_alpha_rangoli::parser;
+use raylib_alpha_rangoli::rangoli::{AlphabetSet, PatternShape, RangoliTextPattern, Script, LOWER_BOUND};
+use raylib_alpha_rangoli::terminal::{self, AnsiRenderer, ColorDepth};
+use raylib_alpha_rangoli::transform::Transform;
+use raylib_alpha_rangoli::truetype::TTF_BASE_SIZE;
 
 #[derive(Parser)]
 struct CliParam {
-    #[arg(short, long)]
-    number: i32,
-    #[arg(short, long)]
-    first_char: char,
-    #[arg(short, long)]
-    last_char: char,
+    #[arg(short, long, required_unless_present_any = ["list_fonts", "check"])]
+    number: Option<i32>,
+    #[arg(short, long, requires = "last_char")]
+    first_char: Option<char>,
+    #[arg(short, long, requires = "first_char")]
+    last_char: Option<char>,
+    /// Named alphabet: latin, latin-upper, greek, greek-upper, devanagari, hiragana or digits.
+    #[arg(long, conflicts_with_all = ["first_char", "tokens", "alphabet_file"])]
+    script: Option<String>,
+    /// Explicit token list, separated by whitespace or commas, e.g. "a c e g".
+    #[arg(long, conflicts_with_all = ["first_char", "alphabet_file"])]
+    tokens: Option<String>,
+    /// UTF-8 file holding a token list, in the same format as --tokens.
+    #[arg(long, conflicts_with = "first_char")]
+    alphabet_file: Option<String>,
+    /// Split every word of --tokens or --alphabet-file into grapheme clusters,
+    /// e.g. "👍🏽👋" into two tokens.
+    #[arg(long)]
+    graphemes: bool,
+    /// Ring geometry: diamond, square, hexagonal or circular.
+    #[arg(long, default_value = "diamond")]
+    shape: String,
+    /// Transformations applied in order, separated by commas: invert, shift:N,
+    /// mirror, rotate-90, rotate-45, caesar:N or transliterate:SCRIPT.
+    /// rotate-45 leaves a checkerboard, with every other token position
+    /// padded with the fill character.
+    #[arg(long, value_delimiter = ',')]
+    transform: Vec<String>,
+    /// Print the pattern to stdout instead of opening a window.
+    #[arg(long)]
+    text: bool,
+    /// Padding character for --text output.
+    #[arg(long, default_value_t = '-', requires = "text")]
+    fill: char,
+    /// Colors of --text output: auto, truecolor, 256 or mono. Auto picks what
+    /// the terminal supports, and mono when the output is not a terminal.
+    #[arg(long, default_value = "auto", requires = "text")]
+    color: String,
+    /// Paint every ring of --text output on a tint of its color.
+    #[arg(long, requires = "text")]
+    ring_fill: bool,
+    /// Write the rendered pattern to a file instead of opening a window (.svg,
+    /// .png, or .gif and .apng for animations).
+    #[arg(long, conflicts_with = "text")]
+    export: Option<String>,
+    /// Token spacing: grid, where columns line up, or proportional, where every
+    /// token takes only its own width.
+    #[arg(long, default_value = "grid")]
+    spacing: String,
+    /// Color palette: classic, marigold, peacock, holi, diya or mono.
+    #[arg(long, default_value = "classic")]
+    palette: String,
+    /// How glyphs pick their palette color: ring, letter or alternate.
+    #[arg(long, default_value = "ring")]
+    color_mode: String,
+    /// Animation: still, reveal, fade or palette.
+    #[arg(long, default_value = "still")]
+    animate: String,
+    /// Animation speed, in rings per second.
+    #[arg(long, default_value_t = 4.0)]
+    speed: f32,
+    /// Restart the animation once it has played through.
+    #[arg(long = "loop")]
+    looping: bool,
+    /// Length of an exported or --text animation in seconds. Defaults to one
+    /// play through, or forever for a looping --text animation on a terminal.
+    #[arg(long)]
+    duration: Option<f32>,
+    /// Frame rate of an exported or --text animation.
+    #[arg(long, default_value_t = animation::DEFAULT_FPS)]
+    fps: u32,
+    /// Bundled font name (see --list-fonts) or path to a font file (.png,
+    /// .fnt, .ttf or .otf).
+    #[arg(long, default_value = fonts::DEFAULT_FONT)]
+    font: String,
+    /// Fonts that fill in the glyphs --font lacks, tried in order and
+    /// separated by commas.
+    #[arg(long, value_delimiter = ',')]
+    fallback_font: Vec<String>,
+    /// Draw glyphs at this size in pixels instead of fitting the pattern to
+    /// the window. TrueType fonts are also loaded at this size.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    font_size: Option<u32>,
+    /// List the bundled fonts and exit.
+    #[arg(long)]
+    list_fonts: bool,
+    /// Check that a text file holds a rangoli, print what was inferred from it and exit.
+    #[arg(long)]
+    check: Option<String>,
+    /// Font family named in SVG output.
+    #[arg(long, default_value = "monospace", requires = "export")]
+    font_family: String,
+}
+
+// Reports an error on stderr, so it never mixes with a pattern piped from
+// stdout, and exits with the code the error maps to.
+
+fn exit_on_error(e: RangoliError) -> ! {
+    eprintln!("{}", e);
+    std::process::exit(e.exit_code());
+}
+
+// Warns about tokens that no font of the chain has a glyph for, only where
+// the fonts are actually drawn with. SVG export names a font family instead.
+
+fn warn_missing_glyphs<M: FontMetrics + ?Sized>(font_path: &std::path::Path, font: &M, alphabet_set: &AlphabetSet) {
+    let missing = fonts::missing_glyphs(font, alphabet_set);
+    if !missing.is_empty() {
+        eprintln!(
+            "Warning: font {} cannot render {} and will draw '?' instead.",
+            font_path.display(),
+            missing.iter().map(|c| c.to_string()).collect::<Vec<String>>().join(" ")
+        );
+    }
 }
 
 fn main() {
-    use crate::rangoli::{AlphabetSet, RangoliTextPattern, LOWER_BOUND, UPPER_BOUND};
-    use crate::raylib_mod::{RLDriver, DEFAULT_FPS, SCREEN_HEIGHT, SCREEN_WIDTH};
-    use raylib::prelude::*;
-    use std::process;
+    use raylib_alpha_rangoli::capture;
+    use raylib_alpha_rangoli::fallback::FontChain;
+    use raylib_alpha_rangoli::layout::{self, SCREEN_HEIGHT, SCREEN_WIDTH};
+    use raylib_alpha_rangoli::metrics::Monospace;
+    use raylib_alpha_rangoli::raster::FramebufferRenderer;
+    use raylib_alpha_rangoli::render::{RangoliRenderer, Scene};
+    use raylib_alpha_rangoli::svg_mod::SvgRenderer;
+    use std::{fs::File, io, io::BufWriter, io::IsTerminal, process};
 
     // *************************
     // Rangoli generation block.
     // *************************
 
     let cli_param = CliParam::parse();
-    if (cli_param.number < LOWER_BOUND) || (cli_param.number > UPPER_BOUND) {
-        println!(
-            "Parameter NUMBER should be between {} and {}\nNUMBER: {}",
-            LOWER_BOUND, UPPER_BOUND, cli_param.number
-        );
-        process::exit(1);
+
+    if cli_param.list_fonts {
+        for name in fonts::BUNDLED_FONTS {
+            println!("{name}");
+        }
+        return;
     }
 
-    let alphabet_range_len = cli_param.last_char as i32 - cli_param.first_char as i32;
-    if alphabet_range_len !=  UPPER_BOUND - 1 {
-        println!("There must be exactly {} sequential characters in the alphabet range.", UPPER_BOUND);
-        println!("For example '-fa -lz' or '-fA -lZ'");
-        process::exit(2);
+    if let Some(path) = &cli_param.check {
+        let parsed = parser::parse_file(path).unwrap_or_else(|e| exit_on_error(e));
+        let alphabet_set = parsed.pattern.get_alphabet_set();
+
+        println!("n: {}", parsed.pattern.get_size());
+        println!("shape: {}", format!("{:?}", parsed.pattern.get_shape()).to_lowercase());
+        println!("alphabet: {}", alphabet_set.get_alphabet().join(" "));
+        println!("delimiter: {:?}", alphabet_set.get_delimiter());
+        match parsed.fill {
+            Some(fill) => println!("fill: {:?}", fill),
+            None => println!("fill: none"),
+        }
+        return;
+    }
+
+    let number = cli_param.number.unwrap_or(LOWER_BOUND);
+
+    let alphabet_set = if let Some(name) = &cli_param.script {
+        Script::from_name(name)
+            .map(AlphabetSet::from_script)
+            .ok_or_else(|| RangoliError::UnknownName {
+                kind: "script",
+                name: name.clone(),
+                choices: Script::NAMES.to_vec(),
+            })
+    } else if let Some(tokens) = &cli_param.tokens {
+        if cli_param.graphemes {
+            AlphabetSet::from_grapheme_str(tokens)
+        } else {
+            AlphabetSet::from_token_str(tokens)
+        }
+    } else if let Some(path) = &cli_param.alphabet_file {
+        if cli_param.graphemes {
+            fs::read_to_string(path)
+                .map_err(RangoliError::from)
+                .and_then(|contents| AlphabetSet::from_grapheme_str(&contents))
+        } else {
+            AlphabetSet::from_file(path)
+        }
+    } else {
+        AlphabetSet::new(cli_param.first_char.unwrap_or('a'), cli_param.last_char.unwrap_or('z'))
     }
+    .unwrap_or_else(|e| exit_on_error(e));
+
+    let shape = PatternShape::from_name(&cli_param.shape).unwrap_or_else(|| {
+        exit_on_error(RangoliError::UnknownName {
+            kind: "shape",
+            name: cli_param.shape.clone(),
+            choices: PatternShape::NAMES.to_vec(),
+        })
+    });
+    let transforms = cli_param
+        .transform
+        .iter()
+        .map(|name| {
+            Transform::from_name(name).ok_or_else(|| RangoliError::UnknownName {
+                kind: "transform",
+                name: name.clone(),
+                choices: Transform::NAMES.to_vec(),
+            })
+        })
+        .collect::<Result<Vec<Transform>, RangoliError>>()
+        .unwrap_or_else(|e| exit_on_error(e));
+    let spacing = Spacing::from_name(&cli_param.spacing).unwrap_or_else(|| {
+        exit_on_error(RangoliError::UnknownName {
+            kind: "spacing",
+            name: cli_param.spacing.clone(),
+            choices: Spacing::NAMES.to_vec(),
+        })
+    });
+    let palette = Palette::by_name(&cli_param.palette).unwrap_or_else(|| {
+        exit_on_error(RangoliError::UnknownName {
+            kind: "palette",
+            name: cli_param.palette.clone(),
+            choices: Palette::NAMES.to_vec(),
+        })
+    });
+    let color_mode = ColorMode::from_name(&cli_param.color_mode).unwrap_or_else(|| {
+        exit_on_error(RangoliError::UnknownName {
+            kind: "color mode",
+            name: cli_param.color_mode.clone(),
+            choices: ColorMode::NAMES.to_vec(),
+        })
+    });
+    let animator = match AnimationMode::from_name(&cli_param.animate) {
+        Some(mode) => Animator::new(mode, cli_param.speed, cli_param.looping),
+        None => exit_on_error(RangoliError::UnknownName {
+            kind: "animation",
+            name: cli_param.animate.clone(),
+            choices: AnimationMode::NAMES.to_vec(),
+        }),
+    };
+
+    let color_depth = match cli_param.color.as_str() {
+        "auto" if std::io::stdout().is_terminal() => ColorDepth::detect(),
+        "auto" => ColorDepth::Mono,
+        name => ColorDepth::from_name(name).unwrap_or_else(|| {
+            exit_on_error(RangoliError::UnknownName {
+                kind: "color",
+                name: name.to_string(),
+                choices: std::iter::once("auto").chain(ColorDepth::NAMES).collect(),
+            })
+        }),
+    };
+    let plain_text = color_depth == ColorDepth::Mono && animator.mode == AnimationMode::Still;
+
+    let rangoli_text_pattern = RangoliTextPattern::with_shape(number, &alphabet_set, shape)
+        .and_then(|rtp| rtp.transform(&transforms))
+        .unwrap_or_else(|e| exit_on_error(e));
+
+    // Headless mode: no raylib window is ever created, so this also works in
+    // shell pipelines and on machines without a display. Plain text is streamed
+    // straight to stdout. The pattern computes its cells as they are written,
+    // so even patterns too large to hold in memory print; transformed ones
+    // hold their grid, though never its text.
+
+    if cli_param.text && plain_text {
+        rangoli_text_pattern
+            .write_text(&mut std::io::stdout().lock(), Some(cli_param.fill))
+            .unwrap_or_else(|e| exit_on_error(e));
+        return;
+    }
+
+    // Colors and animation frames are drawn to the terminal by AnsiRenderer,
+    // which redraws the pattern in place with cursor control sequences. Piped
+    // or redirected, there is no cursor, so only the final frame is printed,
+    // and a looping animation stops after its first cycle.
+
+    if cli_param.text {
+        let glyph_layout =
+            layout::layout_rangoli(&rangoli_text_pattern, &Monospace, spacing, None, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32);
+        let stdout = std::io::stdout();
+        let in_place = stdout.is_terminal();
+        let mut renderer = AnsiRenderer::new(stdout.lock(), Some(cli_param.fill), color_depth, cli_param.ring_fill);
+        renderer.set_in_place(in_place);
+
+        let animated = animator.cycle_duration(layout::ring_count(&glyph_layout.glyphs)) > 0.0;
+        let duration = cli_param.duration.unwrap_or_else(|| match animator.looping && animated && in_place {
+            true => f32::INFINITY,
+            false => capture::default_duration(&glyph_layout.glyphs, &animator, cli_param.fps),
+        });
+        terminal::play(
+            &mut renderer,
+            &rangoli_text_pattern,
+            &glyph_layout,
+            &palette,
+            color_mode,
+            &animator,
+            cli_param.fps,
+            duration,
+        )
+        .unwrap_or_else(|e| exit_on_error(e));
+        return;
+    }
+
+    // A remapped pattern draws tokens of its own alphabet.
 
-    let alphabet_set = AlphabetSet::new(cli_param.first_char, cli_param.last_char);
-    let mut rangoli_text_pattern = RangoliTextPattern::new(cli_param.number, &alphabet_set);
+    let drawn_alphabet = rangoli_text_pattern.get_alphabet_set();
+
+    // Fail fast on a missing font file. TrueType fonts are loaded at the
+    // requested size, if any.
+
+    let font_size = cli_param.font_size.map(|font_size| font_size as f32);
+    let ttf_size = cli_param.font_size.map_or(TTF_BASE_SIZE, |font_size| font_size as usize);
+
+    let font_path = fonts::resolve_font(&cli_param.font).unwrap_or_else(|e| exit_on_error(e));
+    let fallback_paths = cli_param
+        .fallback_font
+        .iter()
+        .map(|name| fonts::resolve_font(name))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap_or_else(|e| exit_on_error(e));
+
+    if let Some(path) = &cli_param.export {
+        let lower_path = path.to_lowercase();
+
+        if lower_path.ends_with(".svg") {
+            // The viewer draws the SVG in whatever font it finds for the
+            // font family, not in the font file given with --font, so those
+            // metrics would measure the wrong glyphs. Widths are estimated
+            // for a monospace font of the same size instead, which is what
+            // the default family asks for.
+
+            let glyph_layout =
+                layout::layout_rangoli(&rangoli_text_pattern, &Monospace, spacing, font_size, SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32);
+            let still = Animator::still();
+            let scene = Scene::new(&rangoli_text_pattern, &glyph_layout, &palette, color_mode, &still);
+
+            let result = File::create(path)
+                .map_err(RangoliError::from)
+                .and_then(|file| SvgRenderer::new(BufWriter::new(file), &cli_param.font_family).render(&scene));
+            if let Err(e) = result {
+                eprintln!("Couldn't write {}: {}", path, e);
+                process::exit(3);
+            }
+        } else if [".png", ".gif", ".apng"].iter().any(|ext| lower_path.ends_with(ext)) {
+            // Bitmap and TrueType fonts are drawn on the CPU, so no GPU or
+            // display is needed. Glyphs are measured with the same fonts.
+
+            let load_raster_font =
+                |path: &std::path::PathBuf| fonts::load_raster_font(path, ttf_size).unwrap_or_else(|e| exit_on_error(e));
+            let font_chain = FontChain::new(load_raster_font(&font_path), fallback_paths.iter().map(load_raster_font).collect());
+            warn_missing_glyphs(&font_path, &font_chain, drawn_alphabet);
+
+            let glyph_layout = layout::layout_rangoli(
+                &rangoli_text_pattern,
+                &font_chain,
+                spacing,
+                font_size,
+                SCREEN_WIDTH as f32,
+                SCREEN_HEIGHT as f32,
+            );
+
+            let result = if lower_path.ends_with(".png") {
+                let still = Animator::still();
+                let mut renderer = FramebufferRenderer::new(&font_chain);
+                renderer
+                    .render(&Scene::new(&rangoli_text_pattern, &glyph_layout, &palette, color_mode, &still))
+                    .and_then(|()| {
+                        let framebuffer = renderer.framebuffer().ok_or_else(|| io::Error::other("no frame was rendered"))?;
+                        Ok(framebuffer.write_png(path)?)
+                    })
+            } else {
+                let duration = cli_param
+                    .duration
+                    .unwrap_or_else(|| capture::default_duration(&glyph_layout.glyphs, &animator, cli_param.fps));
+                let mut capture = capture::Capture::new(
+                    &glyph_layout.glyphs,
+                    &font_chain,
+                    glyph_layout.layout.font_size,
+                    SCREEN_WIDTH as usize,
+                    SCREEN_HEIGHT as usize,
+                    &palette,
+                    color_mode,
+                    &animator,
+                    cli_param.fps,
+                    duration,
+                );
+
+                File::create(path).map_err(RangoliError::from).and_then(|file| {
+                    let writer = BufWriter::new(file);
+                    if lower_path.ends_with(".gif") {
+                        capture::write_gif(writer, &mut capture, animator.looping)
+                    } else {
+                        capture::write_apng(writer, &mut capture, animator.looping)
+                    }
+                    .map_err(RangoliError::from)
+                })
+            };
+
+            if let Err(e) = result {
+                eprintln!("Couldn't write {}: {}", path, e);
+                process::exit(3);
+            }
+        } else {
+            eprintln!("Unsupported export format: {}\nFor example '--export rangoli.svg', '--export rangoli.png' or '--export rangoli.gif'", path);
+            process::exit(2);
+        }
+        return;
+    }
 
     // ********************
     // Raylib set up block.
     // ********************
 
+    // The fonts are also read on the CPU, which tells us up front which tokens
+    // no font of the chain has a glyph for.
+
+    #[cfg(feature = "raylib")]
+    {
+        let load_metrics = |path: &std::path::PathBuf| fonts::load_metrics(path, ttf_size).unwrap_or_else(|e| exit_on_error(e));
+        let font_metrics = FontChain::new(load_metrics(&font_path), fallback_paths.iter().map(load_metrics).collect());
+        warn_missing_glyphs(&font_path, &font_metrics, drawn_alphabet);
+
+        let codepoints = fonts::codepoints(&[&alphabet_set, drawn_alphabet]);
+        open_window(
+            &font_path,
+            &fallback_paths,
+            font_size,
+            &codepoints,
+            number,
+            alphabet_set,
+            shape,
+            transforms,
+            spacing,
+            palette,
+            color_mode,
+            animator,
+        );
+    }
+
+    #[cfg(not(feature = "raylib"))]
+    {
+        eprintln!("This build has no Raylib window. Use --text or --export, or build with the raylib feature.");
+        process::exit(2);
+    }
+}
+
+// Opens the Raylib window and runs the interactive driver until it is closed.
+
+#[cfg(feature = "raylib")]
+#[allow(clippy::too_many_arguments)]
+fn open_window(
+    font_path: &std::path::Path,
+    fallback_paths: &[std::path::PathBuf],
+    font_size: Option<f32>,
+    codepoints: &str,
+    number: i32,
+    alphabet_set: AlphabetSet,
+    shape: PatternShape,
+    transforms: Vec<Transform>,
+    spacing: Spacing,
+    palette: Palette,
+    color_mode: ColorMode,
+    animator: Animator,
+) {
+    use raylib_alpha_rangoli::animation::DEFAULT_FPS;
+    use raylib_alpha_rangoli::layout::{SCREEN_HEIGHT, SCREEN_WIDTH};
+    use raylib_alpha_rangoli::raylib_mod::{self, RLDriver};
+    use raylib::prelude::*;
+
     let (mut rl, thread) = raylib::init()
         .size(SCREEN_WIDTH, SCREEN_HEIGHT)
+        .resizable()
         .title("Rust Alphabet Rangoli (ver. 0.92)")
         .build();
 
     rl.set_target_fps(DEFAULT_FPS);
 
-    // Raylib-Rust calls Raylib-C using FFI. When font loading fails, the following Rust code
-    // does not print the expect() message then panic. Instead, internally the C-library falls
-    // back on its default font, after printing a warning message. See rtext.c LoadBMFont()
-    // TRACELOG message.
+    // Raylib-Rust calls Raylib-C using FFI. When font loading fails, the C-library quietly
+    // falls back on its default font, after printing a warning message. See rtext.c
+    // LoadBMFont() TRACELOG message. Hence the font files were already checked above.
+    // TrueType fonts are rasterized at the requested size, with every glyph the
+    // alphabet needs.
+
+    let ttf_size = font_size.map_or(TTF_BASE_SIZE, |font_size| font_size as usize);
+    let mut load_font = |path: &std::path::Path| {
+        raylib_mod::load_font(&mut rl, &thread, path, ttf_size, codepoints).unwrap_or_else(|e| exit_on_error(e))
+    };
 
-    let font = rl
-        .load_font(&thread, "resources/bogus_font.png")
-        .expect("Couldn't load font!");
+    let font = load_font(font_path);
+    let fallback_fonts = fallback_paths.iter().map(|path| load_font(path)).collect::<Vec<Font>>();
+
+    // The remaining bundled fonts are loaded as well, so the F key can cycle
+    // through them while the window is open.
+
+    let mut loaded_fonts = vec![font];
+    for name in fonts::BUNDLED_FONTS {
+        let bundled_path = fonts::bundled_font_path(name);
+        if bundled_path != font_path {
+            if let Ok(bundled_font) = rl.load_font(&thread, &bundled_path.to_string_lossy()) {
+                loaded_fonts.push(bundled_font);
+            }
+        }
+    }
 
-    let mut rld = RLDriver::build(&mut rl, &thread, &font, &mut rangoli_text_pattern, &alphabet_set);
+    let mut rld = RLDriver::build(
+        &mut rl,
+        &thread,
+        loaded_fonts,
+        fallback_fonts,
+        number,
+        alphabet_set,
+        shape,
+        transforms,
+        spacing,
+        font_size,
+        palette,
+        color_mode,
+        animator,
+    )
+    .unwrap_or_else(|e| exit_on_error(e));
     rld.run();
 }
@@ -0,0 +1,194 @@
+use crate::rangoli;
+
+// Average advance of a monospace glyph, as a fraction of the font size.
+const MONOSPACE_ADVANCE: f32 = 0.6;
+
+/// Glyph measurements of a font. Layout only ever needs these, so it can run
+/// against the font that is actually drawn, whether raylib loaded it or it
+/// was read on the CPU.
+pub trait FontMetrics {
+    /// Size in pixels the glyphs were drawn or rasterized at.
+    fn base_size(&self) -> f32;
+
+    /// Whether the font has a glyph of its own for c.
+    fn has_glyph(&self, c: char) -> bool;
+
+    /// Horizontal advance of c at the base size. Like raylib, characters
+    /// without a glyph advance as much as the '?' they are drawn as.
+    fn advance(&self, c: char) -> f32;
+
+    /// Left and right edge of the ink of c at the base size, relative to the
+    /// pen position it is drawn from. None for blank glyphs such as the space,
+    /// and for fonts that do not know.
+    fn ink_bounds(&self, _c: char) -> Option<(f32, f32)> {
+        None
+    }
+
+    /// Text width in pixels at the given size, following raylib's MeasureTextEx():
+    /// advances scaled from the base size, plus spacing between characters.
+    fn measure_text(&self, text: &str, font_size: f32, spacing: f32) -> f32 {
+        let scale = font_size / self.base_size();
+        let advance = text.chars().map(|c| self.advance(c)).sum::<f32>();
+        let count = text.chars().count();
+
+        advance * scale + count.saturating_sub(1) as f32 * spacing
+    }
+
+    /// Left and right edge of the ink of the whole text at the given size,
+    /// relative to where drawing starts. Text without any known ink spans its
+    /// measured width.
+    fn ink_extent(&self, text: &str, font_size: f32, spacing: f32) -> (f32, f32) {
+        let scale = font_size / self.base_size();
+        let mut pen = 0.0;
+        let mut extent: Option<(f32, f32)> = None;
+
+        for c in text.chars() {
+            if let Some((left, right)) = self.ink_bounds(c) {
+                let (left, right) = (pen + left * scale, pen + right * scale);
+                extent = Some(match extent {
+                    Some((min, max)) => (min.min(left), max.max(right)),
+                    None => (left, right),
+                });
+            }
+            pen += self.advance(c) * scale + spacing;
+        }
+
+        extent.unwrap_or_else(|| (0.0, self.measure_text(text, font_size, spacing)))
+    }
+}
+
+// Borrowed and boxed fonts measure like the font itself, so font chains and
+// trait objects work wherever a font does.
+
+impl<M: FontMetrics + ?Sized> FontMetrics for &M {
+    fn base_size(&self) -> f32 {
+        (**self).base_size()
+    }
+
+    fn has_glyph(&self, c: char) -> bool {
+        (**self).has_glyph(c)
+    }
+
+    fn advance(&self, c: char) -> f32 {
+        (**self).advance(c)
+    }
+
+    fn ink_bounds(&self, c: char) -> Option<(f32, f32)> {
+        (**self).ink_bounds(c)
+    }
+
+    fn measure_text(&self, text: &str, font_size: f32, spacing: f32) -> f32 {
+        (**self).measure_text(text, font_size, spacing)
+    }
+
+    fn ink_extent(&self, text: &str, font_size: f32, spacing: f32) -> (f32, f32) {
+        (**self).ink_extent(text, font_size, spacing)
+    }
+}
+
+impl<M: FontMetrics + ?Sized> FontMetrics for Box<M> {
+    fn base_size(&self) -> f32 {
+        (**self).base_size()
+    }
+
+    fn has_glyph(&self, c: char) -> bool {
+        (**self).has_glyph(c)
+    }
+
+    fn advance(&self, c: char) -> f32 {
+        (**self).advance(c)
+    }
+
+    fn ink_bounds(&self, c: char) -> Option<(f32, f32)> {
+        (**self).ink_bounds(c)
+    }
+
+    fn measure_text(&self, text: &str, font_size: f32, spacing: f32) -> f32 {
+        (**self).measure_text(text, font_size, spacing)
+    }
+
+    fn ink_extent(&self, text: &str, font_size: f32, spacing: f32) -> (f32, f32) {
+        (**self).ink_extent(text, font_size, spacing)
+    }
+}
+
+/// Estimated metrics for when there is no font at hand, such as SVG output
+/// drawn by the viewer in a generic monospace family. Every grapheme cluster
+/// is assumed to be as wide as an average monospace glyph.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Monospace;
+
+impl FontMetrics for Monospace {
+    fn base_size(&self) -> f32 {
+        1.0
+    }
+
+    fn has_glyph(&self, _c: char) -> bool {
+        true
+    }
+
+    fn advance(&self, _c: char) -> f32 {
+        MONOSPACE_ADVANCE
+    }
+
+    fn measure_text(&self, text: &str, font_size: f32, _spacing: f32) -> f32 {
+        rangoli::token_width(text) as f32 * font_size * MONOSPACE_ADVANCE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two glyphs of different widths and a fallback, at a base size of 10.
+    struct TwoGlyphs;
+
+    impl FontMetrics for TwoGlyphs {
+        fn base_size(&self) -> f32 {
+            10.0
+        }
+
+        fn has_glyph(&self, c: char) -> bool {
+            c == 'i' || c == 'm'
+        }
+
+        fn advance(&self, c: char) -> f32 {
+            match c {
+                'i' => 2.0,
+                'm' => 8.0,
+                _ => 5.0,
+            }
+        }
+
+        // 'i' is a thin stroke in the middle of its advance.
+        fn ink_bounds(&self, c: char) -> Option<(f32, f32)> {
+            match c {
+                'i' => Some((0.5, 1.5)),
+                'm' => Some((0.0, 8.0)),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_measure_text() {
+        assert_eq!(0.0, TwoGlyphs.measure_text("", 20.0, 1.0));
+        assert_eq!(4.0, TwoGlyphs.measure_text("i", 20.0, 1.0));
+        assert_eq!(22.0, TwoGlyphs.measure_text("mi", 20.0, 2.0));
+        assert_eq!(TwoGlyphs.measure_text("?", 10.0, 1.0), TwoGlyphs.measure_text("α", 10.0, 1.0));
+
+        assert!((Monospace.measure_text("ab", 10.0, 1.0) - 12.0).abs() < 1e-4);
+        assert_eq!(Monospace.measure_text("e", 10.0, 1.0), Monospace.measure_text("e\u{301}", 10.0, 1.0));
+    }
+
+    #[test]
+    fn test_ink_extent() {
+        assert_eq!((1.0, 3.0), TwoGlyphs.ink_extent("i", 20.0, 1.0));
+        assert_eq!((0.0, 21.0), TwoGlyphs.ink_extent("mi", 20.0, 2.0));
+        assert_eq!((12.0, 14.0), TwoGlyphs.ink_extent(" i", 20.0, 1.0));
+        assert_eq!((0.0, 21.0), TwoGlyphs.ink_extent("  ", 20.0, 1.0));
+
+        let (left, right) = Monospace.ink_extent("ab", 10.0, 1.0);
+        assert!(left == 0.0 && (right - 12.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,192 @@
+/// Backend-neutral color, converted by each renderer into its own color type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Rgba {
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self::new(r, g, b, 255)
+    }
+
+    /// Linear interpolation towards another color, t clamped to [0, 1].
+    pub fn lerp(&self, other: Rgba, t: f32) -> Rgba {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+
+        Rgba::new(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+            mix(self.a, other.a),
+        )
+    }
+
+    /// The same color with its alpha scaled by the given factor in [0, 1].
+    pub fn fade(&self, factor: f32) -> Rgba {
+        Rgba::new(self.r, self.g, self.b, (self.a as f32 * factor.clamp(0.0, 1.0)).round() as u8)
+    }
+
+    pub fn to_hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// How glyph colors are picked from a palette.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorMode {
+    // Gradient across the palette, from the center ring to the outer ring.
+    Ring,
+    // One palette color per letter of the alphabet, cycling when the alphabet is longer.
+    Letter,
+    // The first two palette colors, alternating ring by ring.
+    Alternate,
+}
+
+impl ColorMode {
+    pub const NAMES: [&'static str; 3] = ["ring", "letter", "alternate"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "ring" => Some(ColorMode::Ring),
+            "letter" => Some(ColorMode::Letter),
+            "alternate" => Some(ColorMode::Alternate),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub name: String,
+    pub background: Rgba,
+    // Glyph colors, ordered from the center of the rangoli outwards.
+    pub stops: Vec<Rgba>,
+}
+
+impl Palette {
+    pub const NAMES: [&'static str; 6] = ["classic", "marigold", "peacock", "holi", "diya", "mono"];
+
+    pub fn new(name: &str, background: Rgba, stops: Vec<Rgba>) -> Self {
+        Self {
+            name: name.to_string(),
+            background,
+            stops,
+        }
+    }
+
+    /// Built-in palettes. "classic" is the original saddle brown and dark khaki look.
+    pub fn by_name(name: &str) -> Option<Self> {
+        let palette = match name.to_lowercase().as_str() {
+            "classic" => Palette::new("classic", Rgba::rgb(139, 69, 19), vec![Rgba::rgb(189, 183, 107)]),
+            "marigold" => Palette::new(
+                "marigold",
+                Rgba::rgb(60, 20, 10),
+                vec![Rgba::rgb(255, 214, 0), Rgba::rgb(255, 140, 0), Rgba::rgb(200, 30, 30)],
+            ),
+            "peacock" => Palette::new(
+                "peacock",
+                Rgba::rgb(10, 25, 40),
+                vec![Rgba::rgb(0, 200, 180), Rgba::rgb(30, 90, 200), Rgba::rgb(110, 40, 160)],
+            ),
+            "holi" => Palette::new(
+                "holi",
+                Rgba::rgb(250, 245, 235),
+                vec![
+                    Rgba::rgb(230, 0, 126),
+                    Rgba::rgb(255, 165, 0),
+                    Rgba::rgb(0, 166, 81),
+                    Rgba::rgb(0, 114, 198),
+                    Rgba::rgb(128, 0, 128),
+                ],
+            ),
+            "diya" => Palette::new(
+                "diya",
+                Rgba::rgb(20, 10, 5),
+                vec![Rgba::rgb(255, 240, 200), Rgba::rgb(255, 170, 60), Rgba::rgb(170, 60, 20)],
+            ),
+            "mono" => Palette::new("mono", Rgba::rgb(0, 0, 0), vec![Rgba::rgb(255, 255, 255)]),
+            _ => return None,
+        };
+
+        Some(palette)
+    }
+
+    pub fn all() -> Vec<Self> {
+        Self::NAMES.iter().filter_map(|name| Self::by_name(name)).collect()
+    }
+
+    /// Samples the palette as a piecewise linear gradient, t in [0, 1].
+    pub fn gradient(&self, t: f32) -> Rgba {
+        match self.stops.len() {
+            0 => self.background,
+            1 => self.stops[0],
+            len => {
+                let pos = t.clamp(0.0, 1.0) * (len - 1) as f32;
+                let index = (pos.floor() as usize).min(len - 2);
+                self.stops[index].lerp(self.stops[index + 1], pos - index as f32)
+            }
+        }
+    }
+
+    /// Color of a glyph on the given ring (0 at the center) holding the
+    /// letter at the given alphabet index, in a pattern with `rings` rings.
+    pub fn glyph_color(&self, mode: ColorMode, ring: usize, letter: usize, rings: usize) -> Rgba {
+        if self.stops.is_empty() {
+            return self.background;
+        }
+
+        match mode {
+            ColorMode::Ring => {
+                let t = if rings > 1 { ring as f32 / (rings - 1) as f32 } else { 0.0 };
+                self.gradient(t)
+            }
+            ColorMode::Letter => self.stops[letter % self.stops.len()],
+            ColorMode::Alternate => self.stops[(ring % 2) % self.stops.len()],
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::by_name("classic").unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gradient() {
+        let palette = Palette::new(
+            "test",
+            Rgba::rgb(0, 0, 0),
+            vec![Rgba::rgb(0, 0, 0), Rgba::rgb(100, 200, 0), Rgba::rgb(100, 0, 0)],
+        );
+
+        assert_eq!(Rgba::rgb(0, 0, 0), palette.gradient(0.0));
+        assert_eq!(Rgba::rgb(50, 100, 0), palette.gradient(0.25));
+        assert_eq!(Rgba::rgb(100, 200, 0), palette.gradient(0.5));
+        assert_eq!(Rgba::rgb(100, 0, 0), palette.gradient(1.0));
+        assert_eq!(Rgba::rgb(100, 0, 0), palette.gradient(7.0));
+    }
+
+    #[test]
+    fn test_glyph_color() {
+        let holi = Palette::by_name("Holi").unwrap();
+
+        assert_eq!(holi.stops[0], holi.glyph_color(ColorMode::Ring, 0, 0, 9));
+        assert_eq!(holi.stops[4], holi.glyph_color(ColorMode::Ring, 8, 8, 9));
+        assert_eq!(holi.stops[1], holi.glyph_color(ColorMode::Letter, 0, 6, 9));
+        assert_eq!(holi.stops[1], holi.glyph_color(ColorMode::Alternate, 3, 3, 9));
+        assert_eq!(Palette::NAMES.len(), Palette::all().len());
+    }
+}
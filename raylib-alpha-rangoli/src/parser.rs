@@ -0,0 +1,315 @@
+use std::{fs, path::Path, str::FromStr};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::error::RangoliError;
+use crate::rangoli::{AlphabetSet, PatternShape, RangoliTextPattern};
+
+/// A rangoli read back from text, along with the fill its lines were padded
+/// with. Everything else inferred, n, shape, alphabet and delimiter, is
+/// held by the pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedRangoli {
+    pub pattern: RangoliTextPattern,
+    // None when the lines are trimmed, as in get_rangoli_text().
+    pub fill: Option<char>,
+}
+
+/// Reads rangoli text back into a pattern, the inverse of
+/// RangoliTextPattern::with_shape(). Lines may be padded, as printed by
+/// --text, or trimmed. Text that is not a rangoli fails with
+/// RangoliError::SymmetryBreak, naming the first line and column at fault.
+pub fn parse_rangoli(text: &str) -> Result<ParsedRangoli, RangoliError> {
+    let mut lines = text
+        .lines()
+        .map(|line| line.graphemes(true).collect::<Vec<&str>>())
+        .collect::<Vec<Vec<&str>>>();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+
+    if lines.is_empty() {
+        return Err(symmetry_break(0, None, "there is no text".to_string()));
+    }
+    if lines.len() % 2 == 0 {
+        return Err(symmetry_break(lines.len() - 1, None, format!("{} lines have no middle line", lines.len())));
+    }
+
+    // Every shape the middle line fits is a candidate. The text is checked
+    // against each in turn, and when none matches, the error reported is
+    // the one found furthest into the text.
+
+    let mut infer_error = None;
+    let mut check_error: Option<RangoliError> = None;
+
+    for shape in PatternShape::NAMES.iter().filter_map(|name| PatternShape::from_name(name)) {
+        let pattern = match infer_pattern(&lines, shape) {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                infer_error.get_or_insert(e);
+                continue;
+            }
+        };
+
+        match check_lines(&lines, pattern) {
+            Ok(parsed) => return Ok(parsed),
+            Err(e) => {
+                if check_error.as_ref().is_none_or(|best| position(&e) > position(best)) {
+                    check_error = Some(e);
+                }
+            }
+        }
+    }
+
+    Err(check_error.or(infer_error).unwrap())
+}
+
+/// Reads a rangoli text file, see parse_rangoli().
+pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<ParsedRangoli, RangoliError> {
+    parse_rangoli(&fs::read_to_string(path)?)
+}
+
+impl FromStr for RangoliTextPattern {
+    type Err = RangoliError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_rangoli(s).map(|parsed| parsed.pattern)
+    }
+}
+
+// Infers n, the alphabet and the delimiter from the middle line, the only
+// line that crosses every ring and is never padded.
+
+fn infer_pattern(lines: &[Vec<&str>], shape: PatternShape) -> Result<RangoliTextPattern, RangoliError> {
+    let n = lines.len().div_ceil(2);
+    let row = n - 1;
+    let middle = &lines[row];
+    let half_width = shape.half_width(n, 0);
+    let token_cols = 2 * half_width + 1;
+
+    // token_cols tokens of equal width, with a delimiter between each pair.
+
+    if !(middle.len() + 1).is_multiple_of(token_cols) || middle.len() < 2 * token_cols - 1 {
+        return Err(symmetry_break(
+            row,
+            None,
+            format!("{} columns do not split into {} tokens and their delimiters", middle.len(), token_cols),
+        ));
+    }
+    let width = (middle.len() + 1) / token_cols - 1;
+    let pitch = width + 1;
+
+    let delimiter = if token_cols > 1 {
+        let delimiter = middle[width];
+        for col in (1..token_cols).map(|j| j * pitch - 1) {
+            if middle[col] != delimiter {
+                return Err(symmetry_break(row, Some(col), format!("expected the delimiter {:?}, found {:?}", delimiter, middle[col])));
+            }
+        }
+        match single_char(delimiter) {
+            Some(c) => Some(c),
+            None => return Err(symmetry_break(row, Some(width), format!("the delimiter {:?} is not one character", delimiter))),
+        }
+    } else {
+        None
+    };
+
+    // Narrower tokens are centered in their column with the false token, a
+    // space, which a token never holds.
+
+    let tokens = (0..token_cols)
+        .map(|j| middle[j * pitch..j * pitch + width].concat().trim().to_string())
+        .collect::<Vec<String>>();
+
+    // Ring r is drawn with token r of the alphabet, on both sides.
+
+    let mut alphabet: Vec<Option<String>> = vec![None; n];
+    for k in 0..=half_width {
+        let (left, right) = (&tokens[half_width - k], &tokens[half_width + k]);
+        let col = (half_width + k) * pitch;
+
+        if left != right {
+            return Err(symmetry_break(
+                row,
+                Some(col),
+                format!("{:?} does not mirror {:?} at column {}", right, left, (half_width - k) * pitch + 1),
+            ));
+        }
+
+        let ring = shape.ring(0, k);
+        match &alphabet[ring] {
+            Some(token) if token != right => {
+                return Err(symmetry_break(row, Some(col), format!("ring {} holds both {:?} and {:?}", ring, token, right)));
+            }
+            Some(_) => {}
+            None => alphabet[ring] = Some(right.clone()),
+        }
+    }
+
+    let tokens = alphabet.into_iter().flatten().collect::<Vec<String>>();
+    let alphabet = match delimiter {
+        Some(delimiter) => AlphabetSet::from_tokens_with_delimiter(tokens, delimiter)?,
+        None => AlphabetSet::from_tokens(tokens)?,
+    };
+
+    RangoliTextPattern::with_shape(n as i32, &alphabet, shape)
+}
+
+// Compares every line with the pattern, padded with the fill found in the
+// text, if any.
+
+fn check_lines(lines: &[Vec<&str>], pattern: RangoliTextPattern) -> Result<ParsedRangoli, RangoliError> {
+    let grid = pattern.get_grid();
+
+    // A line longer than its trimmed form is padded, and starts with the fill.
+
+    let mut fill = None;
+    for (row, line) in lines.iter().enumerate() {
+        if line.len() > grid.line(row, None).graphemes(true).count() {
+            match single_char(line[0]) {
+                Some(c) => fill = Some(c),
+                None => return Err(symmetry_break(row, Some(0), format!("the fill {:?} is not one character", line[0]))),
+            }
+            break;
+        }
+    }
+
+    for (row, line) in lines.iter().enumerate() {
+        let expected_line = grid.line(row, fill);
+        let expected = trim_end(expected_line.graphemes(true).collect::<Vec<&str>>());
+        let found = trim_end(line.clone());
+
+        if let Some(col) = (0..expected.len().max(found.len())).find(|&col| expected.get(col) != found.get(col)) {
+            return Err(symmetry_break(
+                row,
+                Some(col),
+                format!("expected {}, found {}", describe(expected.get(col)), describe(found.get(col))),
+            ));
+        }
+    }
+
+    Ok(ParsedRangoli { pattern, fill })
+}
+
+// Editors often strip trailing whitespace, which may well be padding, so it
+// is left out of the comparison.
+
+fn trim_end(mut line: Vec<&str>) -> Vec<&str> {
+    while line.last().is_some_and(|g| g.trim().is_empty()) {
+        line.pop();
+    }
+    line
+}
+
+fn single_char(grapheme: &str) -> Option<char> {
+    let mut chars = grapheme.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+fn describe(grapheme: Option<&&str>) -> String {
+    match grapheme {
+        Some(g) => format!("{:?}", g),
+        None => "the end of the line".to_string(),
+    }
+}
+
+fn position(e: &RangoliError) -> (usize, usize) {
+    match e {
+        RangoliError::SymmetryBreak { row, col, .. } => (*row, col.unwrap_or(0)),
+        _ => (0, 0),
+    }
+}
+
+fn symmetry_break(row: usize, col: Option<usize>, reason: String) -> RangoliError {
+    RangoliError::SymmetryBreak { row, col, reason }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rangoli::Script;
+
+    #[test]
+    fn test_round_trip() {
+        let alphabets = [
+            AlphabetSet::new('a', 'z').unwrap(),
+            AlphabetSet::from_script(Script::GreekLower),
+            AlphabetSet::from_token_str("ka ki ku ke ko").unwrap(),
+            AlphabetSet::from_token_str("a bb ccc").unwrap(),
+            AlphabetSet::from_grapheme_str("👍🏽👋🙏").unwrap(),
+            AlphabetSet::new('a', 'e').unwrap().with_delimiter('|').unwrap(),
+        ];
+
+        for alphabet_set in &alphabets {
+            for name in PatternShape::NAMES {
+                let shape = PatternShape::from_name(name).unwrap();
+                for n in 1..=3 {
+                    let rtp = RangoliTextPattern::with_shape(n, alphabet_set, shape).unwrap();
+
+                    for fill in [Some('-'), Some('.'), Some(' '), None] {
+                        let text = match fill {
+                            Some(fill) => rtp.padded_lines(fill).join("\n"),
+                            None => rtp.get_rangoli_text().0.join("\n"),
+                        };
+                        let parsed = parse_rangoli(&text).unwrap();
+
+                        assert_eq!(rtp.get_rangoli_text(), parsed.pattern.get_rangoli_text(), "{text}");
+                        if n > 1 {
+                            assert_eq!(rtp.get_alphabet_set().get_delimiter(), parsed.pattern.get_alphabet_set().get_delimiter());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_inference() {
+        let parsed = parse_rangoli("....c....\n..c.b.c..\nc.b.a.b.c\n..c.b.c..\n....c....\n").unwrap();
+        assert_eq!(3, parsed.pattern.get_size());
+        assert_eq!(PatternShape::Diamond, parsed.pattern.get_shape());
+        assert_eq!(vec!["a", "b", "c"], *parsed.pattern.get_alphabet_set().get_alphabet());
+        assert_eq!('.', parsed.pattern.get_alphabet_set().get_delimiter());
+        assert_eq!(Some('.'), parsed.fill);
+
+        // The --text output pads with the delimiter itself.
+
+        let parsed = parse_rangoli("--b--\nb-a-b\n--b--").unwrap();
+        assert_eq!(Some('-'), parsed.fill);
+        assert_eq!('-', parsed.pattern.get_alphabet_set().get_delimiter());
+
+        let square = "y-y-y\ny-x-y\ny-y-y".parse::<RangoliTextPattern>().unwrap();
+        assert_eq!(PatternShape::Square, square.get_shape());
+        assert_eq!(vec!["x", "y"], *square.get_alphabet_set().get_alphabet());
+
+        let parsed = parse_rangoli("ki\nki-ka-ki\nki\n").unwrap();
+        assert_eq!(vec!["ka", "ki"], *parsed.pattern.get_alphabet_set().get_alphabet());
+        assert_eq!(None, parsed.fill);
+
+        assert_eq!(1, "α".parse::<RangoliTextPattern>().unwrap().get_size());
+    }
+
+    #[test]
+    fn test_symmetry_breaks() {
+        fn at(text: &str) -> (usize, Option<usize>) {
+            match parse_rangoli(text) {
+                Err(RangoliError::SymmetryBreak { row, col, .. }) => (row, col),
+                other => panic!("{text:?} parsed as {other:?}"),
+            }
+        }
+
+        assert_eq!((0, None), at(""));
+        assert_eq!((1, None), at("--b--\nb-a-b\n"));
+        assert_eq!((1, None), at("--b--\nb-a-b-\n--b--"));
+        assert_eq!((3, Some(4)), at("----c----\n--c-b-c--\nc-b-a-b-c\n--c-x-c--\n----c----"));
+        assert_eq!((2, Some(3)), at("----c----\n--c-b-c--\nc-b+a-b-c\n--c-b-c--\n----c----"));
+        assert_eq!((2, Some(6)), at("----c----\n--c-b-c--\nc-b-a-x-c\n--c-b-c--\n----c----"));
+        assert_eq!((4, Some(8)), at("----c----\n--c-b-c--\nc-b-a-b-c\n--c-b-c--\n----c---"));
+
+        let message = parse_rangoli("--b--\nb-a-b\n--c--").unwrap_err().to_string();
+        assert_eq!("Not a rangoli: line 3, column 3: expected \"b\", found \"c\".", message);
+    }
+}
@@ -1,29 +1,251 @@
-#![allow(warnings)]
+use std::{borrow::Borrow, fmt, fs, io, io::Write, ops::Range, path::Path};
 
-use std::{fmt, process};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::error::RangoliError;
 
 pub static LOWER_BOUND: i32 = 1;
-pub static UPPER_BOUND: i32 = 26;
 
+// Basic gojuon hiragana, in the traditional a-i-u-e-o order.
+const HIRAGANA: &str = "あいうえおかきくけこさしすせそたちつてとなにぬねのはひふへほまみむめもやゆよらりるれろわをん";
+
+/// Named character sets for building an AlphabetSet without typing
+/// every token by hand.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Script {
+    LatinLower,
+    LatinUpper,
+    GreekLower,
+    GreekUpper,
+    Devanagari,
+    Hiragana,
+    Digits,
+}
+
+impl Script {
+    pub const NAMES: [&'static str; 7] = [
+        "latin", "latin-upper", "greek", "greek-upper", "devanagari", "hiragana", "digits",
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "latin" => Some(Script::LatinLower),
+            "latin-upper" => Some(Script::LatinUpper),
+            "greek" => Some(Script::GreekLower),
+            "greek-upper" => Some(Script::GreekUpper),
+            "devanagari" => Some(Script::Devanagari),
+            "hiragana" => Some(Script::Hiragana),
+            "digits" => Some(Script::Digits),
+            _ => None,
+        }
+    }
+
+    pub fn tokens(&self) -> Vec<char> {
+        match self {
+            Script::LatinLower => ('a'..='z').collect(),
+            Script::LatinUpper => ('A'..='Z').collect(),
+            // The Greek blocks are not quite contiguous: lowercase carries the
+            // final sigma, uppercase has an unassigned code point in its place.
+            Script::GreekLower => ('α'..='ω').filter(|c| *c != 'ς').collect(),
+            Script::GreekUpper => ('Α'..='Ω').filter(|c| *c != '\u{03A2}').collect(),
+            // Consonants only, ka through ha.
+            Script::Devanagari => ('क'..='ह').collect(),
+            Script::Hiragana => HIRAGANA.chars().collect(),
+            Script::Digits => ('0'..='9').collect(),
+        }
+    }
+}
+
+/// Geometry of the rings. Every shape assigns tokens the same way, the
+/// first token of the alphabet at the center and ring r using token r, and
+/// differs only in how the distance to the center is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PatternShape {
+    // Manhattan distance, the classic rangoli.
+    #[default]
+    Diamond,
+    // Chebyshev distance.
+    Square,
+    // Flat-topped hexagons, twice as wide as they are tall.
+    Hexagonal,
+    // Euclidean distance, rounded to the nearest ring.
+    Circular,
+}
+
+impl PatternShape {
+    pub const NAMES: [&'static str; 4] = ["diamond", "square", "hexagonal", "circular"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "diamond" => Some(PatternShape::Diamond),
+            "square" => Some(PatternShape::Square),
+            "hexagonal" => Some(PatternShape::Hexagonal),
+            "circular" => Some(PatternShape::Circular),
+            _ => None,
+        }
+    }
+
+    /// Ring of the token dr rows and k tokens away from the center.
+    pub fn ring(&self, dr: usize, k: usize) -> usize {
+        match self {
+            PatternShape::Diamond => dr + k,
+            PatternShape::Square => dr.max(k),
+            PatternShape::Hexagonal => dr.max((dr + k).div_ceil(2)),
+            // The square root of an integer is never exactly halfway between
+            // two integers, so rounding has no ties to break.
+            PatternShape::Circular => ((dr * dr + k * k) as f64).sqrt().round() as usize,
+        }
+    }
+
+    // Tokens on either side of the center token, on the row dr rows away
+    // from the center of an n-ring pattern. Every ring function grows with
+    // k, so the tokens of a row are always contiguous.
+
+    pub(crate) fn half_width(&self, n: usize, dr: usize) -> usize {
+        let center = n - 1;
+        match self {
+            PatternShape::Diamond => center - dr,
+            PatternShape::Square => center,
+            PatternShape::Hexagonal => 2 * center - dr,
+            // round(√(dr² + k²)) ≤ n - 1 exactly when 4k² < (2n - 1)² - 4dr².
+            PatternShape::Circular => ((((2 * n - 1) * (2 * n - 1) - 4 * dr * dr) - 1) / 4).isqrt(),
+        }
+    }
+}
+
+const DEFAULT_DELIMITER: char = '-';
+const DEFAULT_FALSE_TOKEN: char = ' ';
+
+/// Tokens are strings: single characters, grapheme clusters such as emoji
+/// with modifiers or Devanagari conjuncts, or short words and syllables.
+#[derive(Debug, Clone, PartialEq)]
 pub struct AlphabetSet {
-    a_vec: Vec<char>,
+    a_vec: Vec<String>,
     delimiter: char, 
     false_token: char
 }
 
 impl AlphabetSet {
-    pub fn new(lower: char, upper: char) -> Self {
+    pub fn new(lower: char, upper: char) -> Result<Self, RangoliError> {
+        Self::from_tokens((lower..=upper).collect::<Vec<char>>())
+    }
+
+    /// Builds an alphabet from an explicit, possibly non-contiguous, list of
+    /// tokens. The first token sits at the center of the rangoli. Tokens may
+    /// not be empty, nor contain whitespace or control characters, nor the
+    /// default delimiter '-'.
+    pub fn from_tokens<T: Into<String>>(tokens: Vec<T>) -> Result<Self, RangoliError> {
+        Self::from_tokens_with_delimiter(tokens, DEFAULT_DELIMITER)
+    }
+
+    /// Like from_tokens(), except that tokens are checked against the given
+    /// delimiter, so they may hold a '-' when another delimiter is used.
+    pub fn from_tokens_with_delimiter<T: Into<String>>(tokens: Vec<T>, delimiter: char) -> Result<Self, RangoliError> {
+        let tokens = tokens.into_iter().map(|t| t.into()).collect::<Vec<String>>();
+        if tokens.is_empty() {
+            return Err(RangoliError::EmptyAlphabet);
+        }
+        if let Some(token) = tokens.iter().find(|t| t.is_empty() || t.chars().any(|c| c.is_whitespace() || c.is_control())) {
+            return Err(RangoliError::InvalidToken(token.clone()));
+        }
+
         Self {
-            a_vec: (lower..=upper).into_iter().collect::<Vec<char>>(),
-            delimiter: '-',
-            false_token: ' '
+            a_vec: tokens,
+            delimiter: DEFAULT_DELIMITER,
+            false_token: DEFAULT_FALSE_TOKEN
+        }.with_delimiter(delimiter)
+    }
+
+    /// Parses a hand-picked token list such as "a c e g", "α,β,γ" or
+    /// "ka ki ku ke ko". Whitespace and commas separate tokens and are never
+    /// part of them, so every word is one token.
+    pub fn from_token_str(s: &str) -> Result<Self, RangoliError> {
+        Self::from_tokens(
+            s.split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<&str>>(),
+        )
+    }
+
+    /// Like from_token_str(), except that words are split further into
+    /// grapheme clusters, so "क्षत्रज्ञ" gives the three conjuncts and "👍🏽👋"
+    /// the two emoji, modifiers included.
+    pub fn from_grapheme_str(s: &str) -> Result<Self, RangoliError> {
+        Self::from_tokens(
+            s.split(|c: char| c.is_whitespace() || c == ',')
+                .flat_map(|word| word.graphemes(true))
+                .collect::<Vec<&str>>(),
+        )
+    }
+
+    /// Reads a UTF-8 token list from a file, using the same rules as from_token_str().
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, RangoliError> {
+        let contents = fs::read_to_string(path)?;
+        Self::from_token_str(&contents)
+    }
+
+    pub fn from_script(script: Script) -> Self {
+        // The presets are checked like any other alphabet. They are never
+        // empty and never contain the default delimiter, which the tests
+        // confirm for every one of them.
+        Self::from_tokens(script.tokens()).expect("preset alphabets are valid")
+    }
+
+    /// Replaces the delimiter separating tokens within a line. It must be
+    /// printable, and must not appear in any token nor be the false token,
+    /// or the text pattern would be ambiguous.
+    pub fn with_delimiter(mut self, delimiter: char) -> Result<Self, RangoliError> {
+        if delimiter.is_control()
+            || delimiter == self.false_token
+            || self.a_vec.iter().any(|t| t.contains(delimiter))
+        {
+            return Err(RangoliError::InvalidDelimiter(delimiter));
+        }
+
+        self.delimiter = delimiter;
+        Ok(self)
+    }
+
+    /// The same alphabet in uppercase. Scripts without letter case are left
+    /// as they are.
+    pub fn to_uppercase(&self) -> Self {
+        self.map_tokens(|t| t.to_uppercase())
+    }
+
+    /// The same alphabet in lowercase, see to_uppercase().
+    pub fn to_lowercase(&self) -> Self {
+        self.map_tokens(|t| t.to_lowercase())
+    }
+
+    fn map_tokens<F: Fn(&str) -> String>(&self, f: F) -> Self {
+        Self {
+            a_vec: self.a_vec.iter().map(|t| f(t)).collect::<Vec<String>>(),
+            delimiter: self.delimiter,
+            false_token: self.false_token
         }
     }
 
-    pub fn get_alphabet(&self) -> &Vec<char> {
+    pub fn len(&self) -> usize {
+        self.a_vec.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.a_vec.is_empty()
+    }
+
+    pub fn get_alphabet(&self) -> &Vec<String> {
         &self.a_vec
     }
 
+    pub fn get_token(&self, index: usize) -> Option<&str> {
+        self.a_vec.get(index).map(|t| t.as_str())
+    }
+
+    /// Index of the token within the alphabet.
+    pub fn position(&self, token: &str) -> Option<usize> {
+        self.a_vec.iter().position(|t| t == token)
+    }
+
     pub fn get_delimiter(&self) -> char {
         self.delimiter
     }
@@ -33,72 +255,595 @@ impl AlphabetSet {
     }
 }
 
+/// One position of the rangoli grid, as computed by RangoliTextPattern::cell_at().
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    // Index of the token within the alphabet. A delimiter takes the index
+    // of its outer neighbour, as it does the ring.
+    pub token: usize,
+    // Position on the padded grid.
+    pub row: usize,
+    pub col: usize,
+    // Ring in the pattern's shape, 0 at the center. A delimiter belongs to
+    // the ring of its outer neighbour.
+    pub ring: usize,
+    pub is_delimiter: bool,
+}
+
+/// The whole pattern as a 2D grid of typed cells, row-major, the padding
+/// around the rings left empty. Text and glyph output are both derived
+/// from it, rather than from parsing lines of text. A generated grid
+/// computes each cell from its coordinates when asked, so it takes the same
+/// memory whatever n; only a transformed grid stores its cells.
+#[derive(Debug, Clone)]
+pub struct RangoliGrid {
+    rows: usize,
+    cols: usize,
+    cells: GridCells,
+    alphabet: AlphabetSet,
+    // Width in text of every token column, that of the widest token in use.
+    token_width: usize,
+}
+
+// Where the cells of a grid come from.
+
+#[derive(Debug, Clone)]
+enum GridCells {
+    // The closed form of an n-ring pattern, see cell().
+    Generated { n: usize, shape: PatternShape },
+    // Cells moved around by a transformation, row-major.
+    Stored(Vec<Option<Cell>>),
+}
+
+impl RangoliGrid {
+    pub fn new(n: i32, alphabet: &AlphabetSet, shape: PatternShape) -> Result<Self, RangoliError> {
+        validate(n, alphabet)?;
+
+        let n = n as usize;
+        Ok(Self {
+            rows: 2 * n - 1,
+            cols: 4 * shape.half_width(n, 0) + 1,
+            cells: GridCells::Generated { n, shape },
+            alphabet: alphabet.clone(),
+            token_width: max_token_width(n, alphabet),
+        })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The cell at (row, col), None for padding and for positions off the
+    /// grid. O(1), a generated grid computes it from its coordinates.
+    pub fn get(&self, row: usize, col: usize) -> Option<Cell> {
+        if row >= self.rows || col >= self.cols {
+            return None;
+        }
+        match &self.cells {
+            GridCells::Generated { n, shape } => cell(*n, *shape, row, col),
+            GridCells::Stored(cells) => cells[row * self.cols + col],
+        }
+    }
+
+    /// One full row of the grid, padding included.
+    pub fn row(&self, row: usize) -> Vec<Option<Cell>> {
+        (0..self.cols).map(|col| self.get(row, col)).collect::<Vec<Option<Cell>>>()
+    }
+
+    /// Rows from top to bottom, each built when it is reached.
+    pub fn iter_rows(&self) -> impl DoubleEndedIterator<Item = Vec<Option<Cell>>> + ExactSizeIterator + '_ {
+        (0..self.rows).map(|row| self.row(row))
+    }
+
+    /// Number of tokens and delimiters on the grid, padding left out.
+    pub fn cell_count(&self) -> usize {
+        match &self.cells {
+            GridCells::Generated { n, shape } => (0..self.rows)
+                .map(|row| 4 * shape.half_width(*n, row.abs_diff(n - 1)) + 1)
+                .sum(),
+            GridCells::Stored(cells) => cells.iter().flatten().count(),
+        }
+    }
+
+    pub fn get_alphabet_set(&self) -> &AlphabetSet {
+        &self.alphabet
+    }
+
+    /// The token drawn in a cell, None for a delimiter.
+    pub fn token(&self, cell: &Cell) -> Option<&str> {
+        if cell.is_delimiter {
+            None
+        } else {
+            self.alphabet.get_token(cell.token)
+        }
+    }
+
+    /// Width in text of a full row, in grapheme clusters.
+    pub fn text_width(&self) -> usize {
+        let token_cols = self.cols.div_ceil(2);
+        token_cols * self.token_width + (self.cols - token_cols)
+    }
+
+    /// The tokens drawn on the grid, each once, in alphabet order. Ring r of
+    /// a generated grid is drawn with token r, so those are the first n.
+    pub fn used_tokens(&self) -> Vec<&str> {
+        let alphabet = &self.alphabet.a_vec;
+        match &self.cells {
+            GridCells::Generated { n, .. } => alphabet.iter().take(*n).map(String::as_str).collect(),
+            GridCells::Stored(cells) => {
+                let mut used = vec![false; alphabet.len()];
+                for cell in cells.iter().flatten().filter(|cell| !cell.is_delimiter) {
+                    if let Some(used) = used.get_mut(cell.token) {
+                        *used = true;
+                    }
+                }
+                alphabet.iter().zip(used).filter(|(_, used)| *used).map(|(token, _)| token.as_str()).collect()
+            }
+        }
+    }
+
+    /// The text of one row. With a fill character the padding is kept,
+    /// without one the padding at either end is trimmed away, while gaps
+    /// between cells keep their width in the false token.
+    pub fn line(&self, row: usize, fill: Option<char>) -> String {
+        let mut text = Vec::new();
+        // Writing to a Vec never fails.
+        let _ = self.write_line(&mut text, row, fill);
+        String::from_utf8_lossy(&text).into_owned()
+    }
+
+    /// Writes the text of one row, as line() builds it, cell by cell.
+    pub fn write_line<W: Write>(&self, writer: &mut W, row: usize, fill: Option<char>) -> io::Result<()> {
+        let cells = self.row(row);
+        let gap = fill.unwrap_or(self.alphabet.false_token);
+        for col in line_span(&cells, fill) {
+            write_cell(writer, &self.alphabet, self.token_width, col, cells[col].as_ref(), Some(gap))?;
+        }
+        Ok(())
+    }
+
+    /// The text of a single cell, as line() writes it: the token centered in
+    /// its column, the delimiter, or for padding the fill, if any.
+    pub fn cell_text(&self, col: usize, cell: Option<&Cell>, fill: Option<char>) -> String {
+        let mut text = Vec::new();
+        // Writing to a Vec never fails.
+        let _ = write_cell(&mut text, &self.alphabet, self.token_width, col, cell, fill);
+        String::from_utf8_lossy(&text).into_owned()
+    }
+
+    // The tokens alone, row-major on a lattice of rows by token columns,
+    // along with the number of token columns. Delimiters are left out.
+
+    pub(crate) fn token_lattice(&self) -> (usize, Vec<Option<Cell>>) {
+        let token_cols = self.cols.div_ceil(2);
+        let tokens = (0..self.rows * token_cols)
+            .map(|pos| self.get(pos / token_cols, 2 * (pos % token_cols)))
+            .collect::<Vec<Option<Cell>>>();
+        (token_cols, tokens)
+    }
+
+    // The inverse of token_lattice(). Cells are moved to their place on the
+    // grid, and a delimiter is put between every two neighbouring tokens.
+
+    pub(crate) fn from_token_lattice(
+        rows: usize,
+        token_cols: usize,
+        tokens: &[Option<Cell>],
+        alphabet: AlphabetSet,
+    ) -> Self {
+        let cols = 2 * token_cols - 1;
+        let mut cells = vec![None; rows * cols];
+
+        for row in 0..rows {
+            for t in 0..token_cols {
+                let Some(token) = tokens[row * token_cols + t] else {
+                    continue;
+                };
+                cells[row * cols + 2 * t] = Some(Cell { row, col: 2 * t, is_delimiter: false, ..token });
+
+                // A delimiter takes after its outer neighbour: the one in the
+                // outer ring or, within a ring, the one further from the center.
+
+                if let Some(Some(right)) = tokens.get(row * token_cols + t + 1).filter(|_| t + 1 < token_cols) {
+                    let col = 2 * t + 1;
+                    let outer = if token.ring != right.ring {
+                        if token.ring > right.ring { token } else { *right }
+                    } else if col > cols / 2 {
+                        *right
+                    } else {
+                        token
+                    };
+                    cells[row * cols + col] = Some(Cell { row, col, is_delimiter: true, ..outer });
+                }
+            }
+        }
+
+        let mut grid = Self { rows, cols, cells: GridCells::Stored(cells), alphabet, token_width: 1 };
+        grid.token_width = grid.used_token_width();
+        grid
+    }
+
+    // The same cells with the tokens of another alphabet.
+
+    pub(crate) fn with_alphabet(&self, alphabet: AlphabetSet) -> Self {
+        let mut grid = Self { alphabet, ..self.clone() };
+        grid.token_width = grid.used_token_width();
+        grid
+    }
+
+    // Widest of the tokens on the grid.
+
+    fn used_token_width(&self) -> usize {
+        self.used_tokens().into_iter().map(token_width).max().unwrap_or(1)
+    }
+}
+
+// Grids are equal when they draw the same cells with the same tokens,
+// whether the cells are generated or stored.
+
+impl PartialEq for RangoliGrid {
+    fn eq(&self, other: &Self) -> bool {
+        self.rows == other.rows
+            && self.cols == other.cols
+            && self.token_width == other.token_width
+            && self.alphabet == other.alphabet
+            && (0..self.rows).all(|row| (0..self.cols).all(|col| self.get(row, col) == other.get(row, col)))
+    }
+}
+
+/// An n-ring rangoli. Only its grid is kept, and the grid of a generated
+/// pattern is the closed form itself, so cells and lines are computed when
+/// asked for and the pattern takes the same memory whatever n.
+#[derive(Debug, Clone, PartialEq)]
 pub struct RangoliTextPattern {
-    rangoli_lines: Vec<String>,
+    n: i32,
+    alphabet: AlphabetSet,
+    shape: PatternShape,
+    grid: RangoliGrid,
     max_width: i32,
 }
 
 impl RangoliTextPattern {
-    pub fn new(n: i32, alphabet: &AlphabetSet) -> Self {
-        let mut rtp = RangoliTextPattern {
-            rangoli_lines: vec![],
-            max_width: 0,
-        };
+    /// Generates an n-ring diamond rangoli, n being at least LOWER_BOUND and
+    /// at most the number of tokens in the alphabet.
+    pub fn new(n: i32, alphabet: &AlphabetSet) -> Result<Self, RangoliError> {
+        Self::with_shape(n, alphabet, PatternShape::Diamond)
+    }
+
+    /// Generates an n-ring rangoli of the given shape. Nothing is built up
+    /// front beyond checking n against the alphabet.
+    pub fn with_shape(n: i32, alphabet: &AlphabetSet, shape: PatternShape) -> Result<Self, RangoliError> {
+        let grid = RangoliGrid::new(n, alphabet, shape)?;
 
-        let mut rangoli_lines = (-1..=(n - 2)).rev().map(|x| {
-            Self::generate_text_line(n - 1, x, alphabet)
-        }).collect::<Vec<String>>();
+        Ok(RangoliTextPattern {
+            n,
+            alphabet: alphabet.clone(),
+            shape,
+            max_width: grid.text_width() as i32,
+            grid,
+        })
+    }
+
+    /// Every line, trimmed, along with the width of the widest. The lines
+    /// are built on each call; iter() hands them out one at a time instead.
+    pub fn get_rangoli_text(&self) -> (Vec<String>, i32) {
+        (self.iter().collect::<Vec<String>>(), self.max_width)
+    }
 
-        // Up till this point, rangoli_lines has only the upper half of
-        // the overall rangoli pattern. The lower half simply mirrors the upper part.
+    pub fn get_size(&self) -> i32 {
+        self.n
+    }
 
-        let mut mirror_lines = rangoli_lines.clone().into_iter().rev().skip(1).collect::<Vec<String>>();
-        rangoli_lines.append(&mut mirror_lines);
-       
-        rtp.rangoli_lines = rangoli_lines;
-        rtp.max_width = rtp.rangoli_lines.iter().map(|x| x.len()).max().unwrap() as i32;
+    pub fn get_alphabet_set(&self) -> &AlphabetSet {
+        &self.alphabet
+    }
 
-        rtp
+    /// The shape the pattern was generated with, before any transformation.
+    pub fn get_shape(&self) -> PatternShape {
+        self.shape
     }
 
-    pub fn get_rangoli_text(&self) -> (&Vec<String>, i32) {
-        (&self.rangoli_lines, self.max_width as i32)
+    pub fn get_grid(&self) -> &RangoliGrid {
+        &self.grid
     }
 
-    fn generate_text_line(n: i32, m: i32, alphabet: &AlphabetSet) -> String {
+    /// Rows of the full grid, 2n - 1 unless the pattern was rotated.
+    pub fn rows(&self) -> usize {
+        self.grid.rows()
+    }
 
-        fn get_token(x: i32, alphabet: &AlphabetSet) -> String {
-            let c = alphabet.a_vec.get(x as usize);
+    /// Columns of the full grid, tokens and delimiters alike. For a diamond
+    /// this is 4n - 3.
+    pub fn cols(&self) -> usize {
+        self.grid.cols()
+    }
 
-            if let Some(token) = c {
-                token.to_string()
-            } else {
-                alphabet.get_false_token().to_string()
-            }
+    /// Looks up a single position of the grid in O(1), without building any
+    /// strings or cells around it: unless the pattern was transformed, the
+    /// cell is computed from its coordinates alone. Returns None for the
+    /// padding around the rings and for coordinates off the grid. With
+    /// single-character tokens, grid columns are also the columns of
+    /// padded_lines().
+    pub fn cell_at(&self, row: usize, col: usize) -> Option<Cell> {
+        self.grid.get(row, col)
+    }
+
+    // The same pattern drawn from another grid, as derived by a transformation.
+
+    pub(crate) fn with_grid(&self, grid: RangoliGrid) -> Self {
+        RangoliTextPattern {
+            n: self.n,
+            alphabet: grid.get_alphabet_set().clone(),
+            shape: self.shape,
+            max_width: grid.text_width() as i32,
+            grid,
         }
+    }
+
+    /// Centers every line within max_width, padding both sides with the fill
+    /// character, in the classic "----c----" style of the Python original.
+    pub fn padded_lines(&self, fill: char) -> Vec<String> {
+        (0..self.grid.rows())
+            .map(|row| self.grid.line(row, Some(fill)))
+            .collect::<Vec<String>>()
+    }
 
-        // For every rangoli line there is a left hand and a right hand segment  
-        // of tokens to combine, with a pivotal token separating the two parts. 
-        // Given an alphabet set, the starting value ((m+1) or (m+2)-1) of the
-        // iterator chain is the index of this pivotal token.
+    /// Streams every line to writer, each ending in '\n', padded with the
+    /// fill character or trimmed without one. Only one cell is built at a
+    /// time, see write_rangoli().
+    pub fn write_text<W: Write>(&self, writer: &mut W, fill: Option<char>) -> Result<(), RangoliError> {
+        let mut writer = io::BufWriter::new(writer);
+        for row in 0..self.grid.rows() {
+            self.grid.write_line(&mut writer, row, fill)?;
+            writer.write_all(b"\n")?;
+        }
 
-        let mut r_line = ((m+1)..=n).rev().map(|x| get_token(x, &alphabet)).collect::<Vec<String>>();
-        let mut r_line_append = ((m+2)..(n+1)).map(|x| get_token(x, &alphabet)).collect::<Vec<String>>();
-        r_line.append(&mut r_line_append);
+        writer.flush()?;
+        Ok(())
+    }
 
-        r_line.join(&alphabet.get_delimiter().to_string())
+    /// Lines from top to bottom, or bottom to top with rev(), each built
+    /// when it is reached. The pattern itself is left untouched.
+    pub fn iter(&self) -> Lines<&RangoliTextPattern> {
+        Lines::new(self)
     }
 
-    pub fn iter(&self) -> std::slice::Iter<'_, std::string::String> {
-        self.rangoli_lines.iter()
+    /// Every token and delimiter of the pattern, row by row, looked up one
+    /// at a time with cell_at(). Filter on is_delimiter to visit the tokens only.
+    pub fn cells(&self) -> Cells<'_> {
+        Cells {
+            rtp: self,
+            front: 0,
+            back: self.rows() * self.cols(),
+            remaining: self.grid.cell_count(),
+        }
     }
 }
 
-impl Iterator for RangoliTextPattern {
+/// Iterator over the trimmed lines of a RangoliTextPattern, see iter().
+pub struct Lines<P: Borrow<RangoliTextPattern>> {
+    rtp: P,
+    // Rows still to go, front inclusive and back exclusive.
+    front: usize,
+    back: usize,
+}
+
+impl<P: Borrow<RangoliTextPattern>> Lines<P> {
+    fn new(rtp: P) -> Self {
+        let back = rtp.borrow().rows();
+        Self { rtp, front: 0, back }
+    }
+}
+
+impl<P: Borrow<RangoliTextPattern>> Iterator for Lines<P> {
     type Item = String;
 
     fn next(&mut self) -> Option<String> {
-        self.rangoli_lines.pop()
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        Some(self.rtp.borrow().grid.line(self.front - 1, None))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.back - self.front, Some(self.back - self.front))
+    }
+}
+
+impl<P: Borrow<RangoliTextPattern>> DoubleEndedIterator for Lines<P> {
+    fn next_back(&mut self) -> Option<String> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.rtp.borrow().grid.line(self.back, None))
+    }
+}
+
+impl<P: Borrow<RangoliTextPattern>> ExactSizeIterator for Lines<P> {}
+
+/// Iterator over the cells of a RangoliTextPattern, see cells().
+pub struct Cells<'a> {
+    rtp: &'a RangoliTextPattern,
+    // Flat row-major positions on the padded grid, front inclusive and
+    // back exclusive. Padding positions are skipped.
+    front: usize,
+    back: usize,
+    remaining: usize,
+}
+
+impl<'a> Iterator for Cells<'a> {
+    type Item = Cell;
+
+    fn next(&mut self) -> Option<Cell> {
+        let cols = self.rtp.cols();
+        while self.front < self.back {
+            let pos = self.front;
+            self.front += 1;
+            if let Some(cell) = self.rtp.cell_at(pos / cols, pos % cols) {
+                self.remaining -= 1;
+                return Some(cell);
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a> DoubleEndedIterator for Cells<'a> {
+    fn next_back(&mut self) -> Option<Cell> {
+        let cols = self.rtp.cols();
+        while self.front < self.back {
+            self.back -= 1;
+            let pos = self.back;
+            if let Some(cell) = self.rtp.cell_at(pos / cols, pos % cols) {
+                self.remaining -= 1;
+                return Some(cell);
+            }
+        }
+        None
+    }
+}
+
+impl<'a> ExactSizeIterator for Cells<'a> {}
+
+// Checks n against the alphabet before anything is generated.
+
+fn validate(n: i32, alphabet: &AlphabetSet) -> Result<(), RangoliError> {
+    if n < LOWER_BOUND {
+        return Err(RangoliError::InvalidSize { n, min: LOWER_BOUND });
+    }
+    if alphabet.is_empty() {
+        return Err(RangoliError::EmptyAlphabet);
+    }
+    if n as usize > alphabet.len() {
+        return Err(RangoliError::AlphabetTooShort { needed: n as usize, available: alphabet.len() });
+    }
+    Ok(())
+}
+
+/// Width of a token in text, counted in grapheme clusters rather than chars,
+/// so that an emoji with a skin tone modifier counts as one.
+pub fn token_width(token: &str) -> usize {
+    token.graphemes(true).count()
+}
+
+// Widest of the n tokens in use.
+
+fn max_token_width(n: usize, alphabet: &AlphabetSet) -> usize {
+    alphabet.a_vec.iter().take(n).map(|t| token_width(t)).max().unwrap_or(1)
+}
+
+// The cell at (row, col) of an n-ring grid, which must be on the grid.
+
+fn cell(n: usize, shape: PatternShape, row: usize, col: usize) -> Option<Cell> {
+    // Distances from the center of the grid. Tokens sit on even columns,
+    // every other column apart, so the k-th token from the center is 2k
+    // columns away. A delimiter takes after its outer neighbour.
+
+    let dr = row.abs_diff(n - 1);
+    let dc = col.abs_diff(2 * shape.half_width(n, 0));
+    let k = dc.div_ceil(2);
+
+    if k > shape.half_width(n, dr) {
+        return None;
+    }
+    let ring = shape.ring(dr, k);
+
+    // Ring r is drawn with token r of the alphabet.
+
+    Some(Cell { token: ring, row, col, ring, is_delimiter: dc % 2 == 1 })
+}
+
+// The columns of a row a line is written over: all of them with a fill
+// character, without one only those from the first cell to the last.
+
+pub(crate) fn line_span(cells: &[Option<Cell>], fill: Option<char>) -> Range<usize> {
+    if fill.is_some() {
+        return 0..cells.len();
+    }
+    match cells.iter().position(Option::is_some) {
+        Some(first) => first..cells.iter().rposition(Option::is_some).map_or(first, |last| last + 1),
+        None => 0..0,
+    }
+}
+
+// Writes the text of one cell. Tokens narrower than token_width are centered
+// with the false token. Padding is written with the fill character, or left
+// out without one.
+
+fn write_cell<W: Write>(
+    writer: &mut W,
+    alphabet: &AlphabetSet,
+    token_width: usize,
+    col: usize,
+    cell: Option<&Cell>,
+    fill: Option<char>,
+) -> io::Result<()> {
+    let mut utf8 = [0u8; 4];
+    let width = if col.is_multiple_of(2) { token_width } else { 1 };
+
+    match cell {
+        Some(cell) if cell.is_delimiter => {
+            writer.write_all(alphabet.delimiter.encode_utf8(&mut utf8).as_bytes())?;
+        }
+        Some(cell) => {
+            let token = &alphabet.a_vec[cell.token];
+            let pad = width - self::token_width(token);
+            let false_token = alphabet.false_token.encode_utf8(&mut utf8);
+
+            writer.write_all(false_token.repeat(pad / 2).as_bytes())?;
+            writer.write_all(token.as_bytes())?;
+            writer.write_all(false_token.repeat(pad - pad / 2).as_bytes())?;
+        }
+        None => {
+            if let Some(fill) = fill {
+                writer.write_all(fill.encode_utf8(&mut utf8).repeat(width).as_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Streams an n-ring rangoli of the given shape to writer, one line at a
+/// time, each line ending in '\n'. Every cell is computed from its
+/// coordinates, so the extra memory stays constant however large n and the
+/// alphabet are. With a fill character, lines are padded to the full width
+/// as in padded_lines(); without one, they are trimmed as in
+/// get_rangoli_text().
+pub fn write_rangoli<W: Write>(
+    writer: &mut W,
+    n: i32,
+    alphabet: &AlphabetSet,
+    shape: PatternShape,
+    fill: Option<char>,
+) -> Result<(), RangoliError> {
+    RangoliTextPattern::with_shape(n, alphabet, shape)?.write_text(writer, fill)
+}
+
+impl IntoIterator for RangoliTextPattern {
+    type Item = String;
+    type IntoIter = Lines<RangoliTextPattern>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Lines::new(self)
+    }
+}
+
+impl<'a> IntoIterator for &'a RangoliTextPattern {
+    type Item = String;
+    type IntoIter = Lines<&'a RangoliTextPattern>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Lines::new(self)
     }
 }
 
@@ -107,7 +852,7 @@ impl fmt::Display for RangoliTextPattern {
         write!(
             f,
             "\nRangoli Text Pattern:\n{:#?}\nMax Width: {}\n",
-            self.rangoli_lines, self.max_width
+            self.iter().collect::<Vec<String>>(), self.max_width
         )
     }
 }
@@ -115,59 +860,385 @@ impl fmt::Display for RangoliTextPattern {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transform::Transform;
 
-    const rtp_bogus: [&str; 1] = ["z"];
-    const rtp_01_data: [&str; 1] = ["a"];
-    const rtp_03_data: [&str; 3] = ["c", "c-b-c", "c-b-a-b-c"];
+    const RTP_BOGUS: [&str; 1] = ["z"];
+    const RTP_01_DATA: [&str; 1] = ["a"];
+    const RTP_03_DATA: [&str; 5] = ["c", "c-b-c", "c-b-a-b-c", "c-b-c", "c"];
+
+    // The character a cell is printed as, for single-character alphabets.
+    fn cell_char(alphabet_set: &AlphabetSet, cell: &Cell) -> char {
+        if cell.is_delimiter {
+            alphabet_set.get_delimiter()
+        } else {
+            alphabet_set.get_token(cell.token).and_then(|t| t.chars().next()).unwrap()
+        }
+    }
 
     #[test]
     fn test_vec_string() {
-        let alphabet_set = AlphabetSet::new('a', 'z');
-        let rtp_01 = RangoliTextPattern::new(1, &alphabet_set);
-        let rtp_03 = RangoliTextPattern::new(3, &alphabet_set);
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+        let rtp_01 = RangoliTextPattern::new(1, &alphabet_set).unwrap();
+        let rtp_03 = RangoliTextPattern::new(3, &alphabet_set).unwrap();
 
         let (rtp_01_vec, _) = rtp_01.get_rangoli_text();
-        assert_eq!(*rtp_01_vec.get(0).unwrap(), rtp_01_data[0]);
-        assert_ne!(*rtp_01_vec.get(0).unwrap(), rtp_bogus[0]);
+        assert_eq!(*rtp_01_vec.first().unwrap(), RTP_01_DATA[0]);
+        assert_ne!(*rtp_01_vec.first().unwrap(), RTP_BOGUS[0]);
 
         let (rtp_03_vec, _) = rtp_03.get_rangoli_text();
-        assert_eq!(rtp_03_data[0], rtp_03_vec.get(0).unwrap());
-        assert_eq!(rtp_03_data[1], rtp_03_vec.get(1).unwrap());
-        assert_eq!(rtp_03_data[2], rtp_03_vec.get(2).unwrap());
-        assert_eq!(None, rtp_03_vec.get(3));
+        assert_eq!(RTP_03_DATA[0], rtp_03_vec.first().unwrap());
+        assert_eq!(RTP_03_DATA[1], rtp_03_vec.get(1).unwrap());
+        assert_eq!(RTP_03_DATA[2], rtp_03_vec.get(2).unwrap());
+        assert_eq!(RTP_03_DATA[3], rtp_03_vec.get(3).unwrap());
+        assert_eq!(RTP_03_DATA[4], rtp_03_vec.get(4).unwrap());
+        assert_eq!(None, rtp_03_vec.get(5));
+    }
+
+    #[test]
+    fn test_custom_alphabets() {
+        let alphabet_set = AlphabetSet::from_token_str("x, q z").unwrap();
+        assert_eq!(*alphabet_set.get_alphabet(), vec!["x", "q", "z"]);
+
+        let rtp_03 = RangoliTextPattern::new(3, &alphabet_set).unwrap();
+        let (rtp_03_vec, _) = rtp_03.get_rangoli_text();
+        assert_eq!("z-q-x-q-z", rtp_03_vec.get(2).unwrap());
+
+        let greek = AlphabetSet::from_script(Script::GreekLower);
+        assert_eq!(24, greek.len());
+        let rtp_02 = RangoliTextPattern::new(2, &greek).unwrap();
+        let (rtp_02_vec, _) = rtp_02.get_rangoli_text();
+        assert_eq!("β-α-β", rtp_02_vec.get(1).unwrap());
+
+        assert_eq!(24, AlphabetSet::from_script(Script::GreekUpper).len());
+        assert_eq!(10, AlphabetSet::from_script(Script::Digits).len());
+        assert_eq!(46, AlphabetSet::from_script(Script::Hiragana).len());
+        assert_eq!(Some(Script::Devanagari), Script::from_name("Devanagari"));
+        for name in Script::NAMES {
+            assert!(!AlphabetSet::from_script(Script::from_name(name).unwrap()).is_empty());
+        }
+
+        let upper = AlphabetSet::new('a', 'c').unwrap().to_uppercase();
+        assert_eq!(*upper.get_alphabet(), vec!["A", "B", "C"]);
+        assert_eq!(*upper.to_lowercase().get_alphabet(), vec!["a", "b", "c"]);
+        assert_eq!(*greek.to_uppercase().get_alphabet(), AlphabetSet::from_script(Script::GreekUpper).get_alphabet().clone());
+        assert_eq!(*AlphabetSet::from_token_str("1 ß").unwrap().to_uppercase().get_alphabet(), vec!["1", "SS"]);
+        assert_eq!(None, Script::from_name("klingon"));
+    }
+
+    #[test]
+    fn test_multi_char_tokens() {
+        let syllables = AlphabetSet::from_token_str("ka ki ku").unwrap();
+        assert_eq!(*syllables.get_alphabet(), vec!["ka", "ki", "ku"]);
+        assert_eq!(Some(1), syllables.position("ki"));
+        let rtp_02 = RangoliTextPattern::new(2, &syllables).unwrap();
+        assert_eq!(vec!["ki", "ki-ka-ki", "ki"], rtp_02.get_rangoli_text().0);
+        assert_eq!(8, rtp_02.get_rangoli_text().1);
+
+        // Narrower tokens are padded with the false token to the width of
+        // the widest one, so that every token column lines up.
+
+        let mixed = AlphabetSet::from_token_str("a bb").unwrap();
+        let rtp_02 = RangoliTextPattern::new(2, &mixed).unwrap();
+        assert_eq!(vec!["...bb...", "bb-a -bb", "...bb..."], rtp_02.padded_lines('.'));
+        assert_eq!(Some("a"), rtp_02.get_grid().token(&rtp_02.cell_at(1, 2).unwrap()));
+        assert_eq!(None, rtp_02.get_grid().token(&rtp_02.cell_at(1, 1).unwrap()));
+
+        // Grapheme clusters count as one token, and one column of text.
+
+        let emoji = AlphabetSet::from_grapheme_str("👍🏽👋").unwrap();
+        assert_eq!(*emoji.get_alphabet(), vec!["👍🏽", "👋"]);
+        assert_eq!(1, token_width("👍🏽"));
+        assert_eq!("👋-👍🏽-👋", RangoliTextPattern::new(2, &emoji).unwrap().get_rangoli_text().0[1]);
+
+        let conjuncts = AlphabetSet::from_grapheme_str("क्ष त्र").unwrap();
+        assert_eq!(*conjuncts.get_alphabet(), vec!["क्ष", "त्र"]);
+        assert_eq!(2, AlphabetSet::from_token_str("क्ष त्र").unwrap().len());
+    }
+
+    #[test]
+    fn test_errors() {
+        let alphabet_set = AlphabetSet::new('a', 'e').unwrap();
+
+        assert!(matches!(RangoliTextPattern::new(0, &alphabet_set), Err(RangoliError::InvalidSize { n: 0, .. })));
+        assert!(matches!(
+            RangoliTextPattern::new(6, &alphabet_set),
+            Err(RangoliError::AlphabetTooShort { needed: 6, available: 5 })
+        ));
+        assert!(RangoliTextPattern::new(5, &alphabet_set).is_ok());
+
+        assert!(matches!(AlphabetSet::new('z', 'a'), Err(RangoliError::EmptyAlphabet)));
+        assert!(matches!(AlphabetSet::from_token_str(" , "), Err(RangoliError::EmptyAlphabet)));
+        assert!(matches!(AlphabetSet::from_token_str("a - b"), Err(RangoliError::InvalidDelimiter('-'))));
+        assert!(matches!(AlphabetSet::from_token_str("a -b"), Err(RangoliError::InvalidDelimiter('-'))));
+        assert!(matches!(AlphabetSet::from_tokens(vec!["a", ""]), Err(RangoliError::InvalidToken(_))));
+        assert!(matches!(AlphabetSet::from_tokens(vec!["a b"]), Err(RangoliError::InvalidToken(_))));
+        assert!(matches!(alphabet_set.clone().with_delimiter('c'), Err(RangoliError::InvalidDelimiter('c'))));
+        assert!(matches!(alphabet_set.clone().with_delimiter(' '), Err(RangoliError::InvalidDelimiter(' '))));
+        assert!(matches!(AlphabetSet::from_tokens_with_delimiter(vec!["a|b"], '|'), Err(RangoliError::InvalidDelimiter('|'))));
+        assert!(matches!(AlphabetSet::from_tokens_with_delimiter(vec!["a", " "], '|'), Err(RangoliError::InvalidToken(_))));
+        assert!(matches!(AlphabetSet::from_file("no/such/file.txt"), Err(RangoliError::Io(_))));
+
+        // A token may hold the default delimiter when another one is used.
+        let hyphenated = AlphabetSet::from_tokens_with_delimiter(vec!["x-y", "z"], '|').unwrap();
+        assert_eq!(" z |x-y| z ", RangoliTextPattern::new(2, &hyphenated).unwrap().get_rangoli_text().0[1]);
+
+        let dotted = alphabet_set.with_delimiter('.').unwrap();
+        let rtp_02 = RangoliTextPattern::new(2, &dotted).unwrap();
+        assert_eq!("b.a.b", rtp_02.get_rangoli_text().0[1]);
+    }
+
+    #[test]
+    fn test_cell_at() {
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+
+        for n in [1, 2, 5, 26] {
+            let rtp = RangoliTextPattern::new(n, &alphabet_set).unwrap();
+            let padded_lines = rtp.padded_lines(' ');
+
+            assert_eq!(rtp.rows(), padded_lines.len());
+            for (row, line) in padded_lines.iter().enumerate() {
+                assert_eq!(rtp.cols(), line.chars().count());
+                for (col, c) in line.chars().enumerate() {
+                    assert_eq!(c, rtp.cell_at(row, col).map(|cell| cell_char(&alphabet_set, &cell)).unwrap_or(' '));
+                }
+            }
+        }
+
+        let rtp_03 = RangoliTextPattern::new(3, &alphabet_set).unwrap();
+        assert_eq!(Some(Cell { token: 0, row: 2, col: 4, ring: 0, is_delimiter: false }), rtp_03.cell_at(2, 4));
+        assert_eq!(Some(Cell { token: 1, row: 2, col: 3, ring: 1, is_delimiter: true }), rtp_03.cell_at(2, 3));
+        assert_eq!(Some(Cell { token: 2, row: 1, col: 2, ring: 2, is_delimiter: false }), rtp_03.cell_at(1, 2));
+        assert_eq!(None, rtp_03.cell_at(0, 0));
+        assert_eq!(None, rtp_03.cell_at(5, 4));
+        assert_eq!(None, rtp_03.cell_at(2, 9));
+    }
+
+    #[test]
+    fn test_write_rangoli() {
+        let greek = AlphabetSet::from_script(Script::GreekLower);
+        let rtp_04 = RangoliTextPattern::new(4, &greek).unwrap();
+
+        let mut padded = Vec::new();
+        write_rangoli(&mut padded, 4, &greek, PatternShape::Diamond, Some('.')).unwrap();
+        assert_eq!(rtp_04.padded_lines('.').join("\n") + "\n", String::from_utf8(padded).unwrap());
+
+        let mut trimmed = Vec::new();
+        write_rangoli(&mut trimmed, 4, &greek, PatternShape::Diamond, None).unwrap();
+        assert_eq!(rtp_04.get_rangoli_text().0.join("\n") + "\n", String::from_utf8(trimmed).unwrap());
+
+        assert!(matches!(write_rangoli(&mut Vec::new(), 25, &greek, PatternShape::Square, None), Err(RangoliError::AlphabetTooShort { .. })));
+    }
+
+    #[test]
+    fn test_lazy_pattern() {
+        // Neither cells nor lines are built up front, so a pattern with
+        // billions of cells is made at once and read a line at a time.
+
+        let n: usize = 50_000;
+        let alphabet_set = AlphabetSet::from_tokens((0..n).map(|i| format!("t{i}")).collect::<Vec<String>>()).unwrap();
+        let rtp = RangoliTextPattern::new(n as i32, &alphabet_set).unwrap();
+
+        let mut lines = rtp.iter();
+        assert_eq!(2 * n - 1, lines.len());
+        assert_eq!(Some("t49999".to_string()), lines.next());
+        assert_eq!(Some("t49999".to_string()), lines.next_back());
+        assert_eq!(2 * n - 3, lines.len());
+        assert_eq!(Some("t49999-t49998-t49999".to_string()), lines.next());
+        assert_eq!(Some(0), rtp.cell_at(n - 1, 2 * n - 2).map(|cell| cell.token));
+
+        // A transformed pattern streams the same text it hands out in lines.
+
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+        let rtp_03 = RangoliTextPattern::new(3, &alphabet_set).unwrap().transform(&[Transform::ShiftRings(1), Transform::Rotate90]).unwrap();
+        let mut padded = Vec::new();
+        rtp_03.write_text(&mut padded, Some('.')).unwrap();
+        assert_eq!(rtp_03.padded_lines('.').join("\n") + "\n", String::from_utf8(padded).unwrap());
+        assert_eq!(rtp_03.get_rangoli_text().0, rtp_03.into_iter().collect::<Vec<String>>());
+    }
+
+    #[test]
+    fn test_shapes() {
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+
+        let square = RangoliTextPattern::with_shape(3, &alphabet_set, PatternShape::Square).unwrap();
+        assert_eq!(vec!["c-c-c-c-c", "c-b-b-b-c", "c-b-a-b-c", "c-b-b-b-c", "c-c-c-c-c"], square.get_rangoli_text().0);
+
+        let hexagonal = RangoliTextPattern::with_shape(3, &alphabet_set, PatternShape::Hexagonal).unwrap();
+        assert_eq!("c-c-b-b-a-b-b-c-c", hexagonal.get_rangoli_text().0[2]);
+        assert_eq!("c-c-c-c-c", hexagonal.get_rangoli_text().0[0]);
+
+        let circular = RangoliTextPattern::with_shape(4, &alphabet_set, PatternShape::Circular).unwrap();
+        assert_eq!("d-d-d", circular.get_rangoli_text().0[0]);
+        assert_eq!("d-c-b-a-b-c-d", circular.get_rangoli_text().0[3]);
+
+        // Every shape shares the same token assignment, and cell_at() agrees
+        // with the generated text.
+
+        for name in PatternShape::NAMES {
+            let shape = PatternShape::from_name(name).unwrap();
+            for n in [1, 2, 6, 13] {
+                let rtp = RangoliTextPattern::with_shape(n, &alphabet_set, shape).unwrap();
+                assert_eq!(shape, rtp.get_shape());
+                assert_eq!(rtp.cells().len(), rtp.cells().count());
+
+                for (row, line) in rtp.padded_lines(' ').iter().enumerate() {
+                    assert_eq!(rtp.cols(), line.chars().count());
+                    for (col, c) in line.chars().enumerate() {
+                        match rtp.cell_at(row, col) {
+                            Some(cell) if !cell.is_delimiter => {
+                                assert_eq!(c, cell_char(&alphabet_set, &cell));
+                                assert_eq!(cell.token, cell.ring);
+                                assert!(cell.ring < n as usize);
+                            }
+                            Some(_) => assert_eq!(c, '-'),
+                            None => assert_eq!(c, ' '),
+                        }
+                    }
+                }
+            }
+        }
+        assert_eq!(None, PatternShape::from_name("star"));
+    }
+
+    #[test]
+    fn test_grid() {
+        let alphabet_set = AlphabetSet::from_token_str("x y z").unwrap();
+        let grid = RangoliGrid::new(2, &alphabet_set, PatternShape::Diamond).unwrap();
+
+        assert_eq!((3, 5), (grid.rows(), grid.cols()));
+        assert_eq!(None, grid.get(0, 0));
+        assert_eq!(Some(Cell { token: 0, row: 1, col: 2, ring: 0, is_delimiter: false }), grid.get(1, 2));
+        assert_eq!(Some(Cell { token: 1, row: 1, col: 3, ring: 1, is_delimiter: true }), grid.get(1, 3));
+        assert_eq!(None, grid.get(3, 2));
+        assert_eq!("y-x-y", grid.line(1, None));
+        assert_eq!("..y..", grid.line(2, Some('.')));
+        assert_eq!(3, grid.iter_rows().count());
+        assert_eq!(1, grid.row(0).iter().flatten().count());
+
+        // The pattern's text and cells are all taken from its grid.
+
+        let rtp = RangoliTextPattern::with_shape(3, &alphabet_set, PatternShape::Square).unwrap();
+        let grid = rtp.get_grid();
+        for (row, line) in rtp.iter().enumerate() {
+            assert_eq!(line, grid.line(row, None));
+        }
+        assert!(rtp.cells().all(|cell| grid.get(cell.row, cell.col) == Some(cell)));
+        assert!(matches!(RangoliGrid::new(4, &alphabet_set, PatternShape::Square), Err(RangoliError::AlphabetTooShort { .. })));
+    }
+
+    #[test]
+    fn test_grid_closed_form() {
+        // A generated grid holds no cells, so a pattern far too large to
+        // print still answers every lookup straight away.
+
+        let n: usize = 50_000;
+        let alphabet_set = AlphabetSet::from_tokens((0..n).map(|i| format!("t{i}")).collect::<Vec<String>>()).unwrap();
+        let grid = RangoliGrid::new(n as i32, &alphabet_set, PatternShape::Diamond).unwrap();
+
+        assert_eq!((2 * n - 1, 4 * n - 3), (grid.rows(), grid.cols()));
+        assert_eq!(Some(Cell { token: 0, row: n - 1, col: 2 * n - 2, ring: 0, is_delimiter: false }), grid.get(n - 1, 2 * n - 2));
+        assert_eq!(Some(n - 1), grid.get(0, 2 * n - 2).map(|cell| cell.ring));
+        assert_eq!(Some(true), grid.get(n - 1, 1).map(|cell| cell.is_delimiter));
+        assert_eq!(None, grid.get(0, 2 * n - 3));
+
+        // 2n² - 2n + 1 tokens, and a delimiter between neighbours on a row.
+        let tokens = 2 * n * n - 2 * n + 1;
+        assert_eq!(2 * tokens - grid.rows(), grid.cell_count());
+
+        // Counted without visiting the cells, and the same as visiting them.
+        for name in PatternShape::NAMES {
+            let shape = PatternShape::from_name(name).unwrap();
+            let grid = RangoliGrid::new(5, &alphabet_set, shape).unwrap();
+            assert_eq!(grid.iter_rows().flatten().flatten().count(), grid.cell_count());
+        }
+    }
+
+    #[test]
+    fn test_padded_lines() {
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+        let rtp_03 = RangoliTextPattern::new(3, &alphabet_set).unwrap();
+        let padded = rtp_03.padded_lines('-');
+
+        assert_eq!(5, padded.len());
+        assert_eq!("----c----", padded[0]);
+        assert_eq!("--c-b-c--", padded[1]);
+        assert_eq!("c-b-a-b-c", padded[2]);
+        assert_eq!(padded[1], padded[3]);
+        assert_eq!("....c....", rtp_03.padded_lines('.')[0]);
     }
 
     #[test]
     fn test_iter_consume() {
-        let alphabet_set = AlphabetSet::new('a', 'z');
-        let mut rtp_01 = RangoliTextPattern::new(1, &alphabet_set);
-        assert_eq!(rtp_01_data[0], rtp_01.next().unwrap());
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+        let mut rtp_01 = RangoliTextPattern::new(1, &alphabet_set).unwrap().into_iter();
+        assert_eq!(RTP_01_DATA[0], rtp_01.next().unwrap());
         assert_eq!(None, rtp_01.next());
 
-        let mut rtp_03 = RangoliTextPattern::new(3, &alphabet_set);
-        assert_eq!(rtp_03_data[2], rtp_03.next().unwrap());
-        assert_eq!(rtp_03_data[1], rtp_03.next().unwrap());
-        assert_eq!(rtp_03_data[0], rtp_03.next().unwrap());
+        let mut rtp_03 = RangoliTextPattern::new(3, &alphabet_set).unwrap().into_iter();
+        assert_eq!(5, rtp_03.len());
+        assert_eq!(RTP_03_DATA[0], rtp_03.next().unwrap());
+        assert_eq!(RTP_03_DATA[4], rtp_03.next_back().unwrap());
+        assert_eq!(RTP_03_DATA[1], rtp_03.next().unwrap());
+        assert_eq!(RTP_03_DATA[2], rtp_03.next().unwrap());
+        assert_eq!(RTP_03_DATA[3], rtp_03.next().unwrap());
         assert_eq!(None, rtp_03.next());
     }
 
     #[test]
     fn test_iter_consume_loop() {
-        let alphabet_set = AlphabetSet::new('a', 'z');
-        let mut rtp_03 = RangoliTextPattern::new(3, &alphabet_set);
-        let mut idx: i8 = 2;
-        while idx >= 0 {
-            assert_eq!(rtp_03_data[idx as usize], rtp_03.next().unwrap());
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+        let rtp_03 = RangoliTextPattern::new(3, &alphabet_set).unwrap();
 
-            idx = idx - 1;
+        // Bottom-up, twice over, without consuming the pattern.
+
+        for _ in 0..2 {
+            let mut idx: i8 = 4;
+            for line in rtp_03.iter().rev() {
+                assert_eq!(RTP_03_DATA[idx as usize], line);
+
+                idx -= 1;
+            }
+            assert_eq!(-1, idx);
         }
-        assert_eq!(None, rtp_03.next());
+
+        for (idx, line) in (&rtp_03).into_iter().enumerate() {
+            assert_eq!(RTP_03_DATA[idx], line);
+        }
+    }
+
+    #[test]
+    fn test_cells() {
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+
+        for n in [1, 2, 3, 7] {
+            let rtp = RangoliTextPattern::new(n, &alphabet_set).unwrap();
+            let cells = rtp.cells();
+            let count = rtp.iter().map(|line| line.chars().count()).sum::<usize>();
+            assert_eq!(count, cells.len());
+            assert_eq!(count, cells.count());
+
+            let forward = rtp.cells().collect::<Vec<Cell>>();
+            let mut backward = rtp.cells().rev().collect::<Vec<Cell>>();
+            backward.reverse();
+            assert_eq!(forward, backward);
+        }
+
+        let rtp_03 = RangoliTextPattern::new(3, &alphabet_set).unwrap();
+        let tokens = rtp_03.cells().filter(|cell| !cell.is_delimiter).collect::<Vec<Cell>>();
+        assert_eq!(13, tokens.len());
+        assert_eq!(Cell { token: 2, row: 0, col: 4, ring: 2, is_delimiter: false }, tokens[0]);
+        assert_eq!(Cell { token: 0, row: 2, col: 4, ring: 0, is_delimiter: false }, tokens[6]);
+
+        let mut cells = rtp_03.cells();
+        cells.next();
+        cells.next_back();
+        assert_eq!(rtp_03.cells().len() - 2, cells.len());
     }
 
     #[test]
     fn test_iter_print_loop() {
-        let alphabet_set = AlphabetSet::new('a', 'z');
-        let mut rtp_03 = RangoliTextPattern::new(3, &alphabet_set);
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+        let rtp_03 = RangoliTextPattern::new(3, &alphabet_set).unwrap();
         println!();
         for (idx, line) in rtp_03.iter().enumerate() {
             println!("{idx}: {line}");
@@ -0,0 +1,242 @@
+use std::{fs::File, io, io::BufWriter, path::Path};
+
+use crate::animation::Animator;
+use crate::bitmap_font::BitmapFont;
+use crate::error::RangoliError;
+use crate::layout::{self, AlphaToDisplay};
+use crate::metrics::FontMetrics;
+use crate::palette::{ColorMode, Palette, Rgba};
+use crate::render::{RangoliRenderer, Scene};
+
+/// An RGBA image in memory that glyphs are drawn into on the CPU.
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize, background: Rgba) -> Self {
+        let pixels = [background.r, background.g, background.b, background.a].repeat(width * height);
+        Self { width, height, pixels }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Paints every pixel the background color, ready for the next frame.
+    pub fn clear(&mut self, background: Rgba) {
+        for px in self.pixels.chunks_exact_mut(4) {
+            px.copy_from_slice(&[background.r, background.g, background.b, background.a]);
+        }
+    }
+
+    /// Draws one glyph with the pen at (x, y), the top of the line.
+    pub fn draw_glyph<F: RasterFont + ?Sized>(&mut self, font: &F, c: char, x: f32, y: f32, font_size: f32, tint: Rgba) {
+        font.draw_glyph(self, c, x, y, font_size, tint);
+    }
+
+    /// Draws a whole token left to right, one glyph after the other, the way
+    /// DrawTextEx() does with a spacing of 1.0. Matches measure_text().
+    pub fn draw_text<F: RasterFont + ?Sized>(&mut self, font: &F, text: &str, x: f32, y: f32, font_size: f32, tint: Rgba) {
+        let scale = font_size / font.base_size();
+        let mut pen_x = x;
+
+        for c in text.chars() {
+            self.draw_glyph(font, c, pen_x, y, font_size, tint);
+            pen_x += font.advance(c) * scale + 1.0;
+        }
+    }
+
+    // Alpha blends one pixel over what is already there. Pixels off the
+    // framebuffer are skipped.
+
+    pub(crate) fn blend(&mut self, x: usize, y: usize, src: [u8; 4]) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let i = (y * self.width + x) * 4;
+        let alpha = src[3] as u32;
+
+        for (dst, &value) in self.pixels[i..i + 3].iter_mut().zip(&src[..3]) {
+            *dst = ((value as u32 * alpha + *dst as u32 * (255 - alpha) + 127) / 255) as u8;
+        }
+        let dst_alpha = self.pixels[i + 3] as u32;
+        self.pixels[i + 3] = (alpha + (dst_alpha * (255 - alpha) + 127) / 255) as u8;
+    }
+
+    pub fn write_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header().map_err(to_io_error)?;
+        writer.write_image_data(&self.pixels).map_err(to_io_error)
+    }
+}
+
+/// A font that can draw its own glyphs into a framebuffer. Pens advance by
+/// the font's metrics, so what is drawn matches what the layout measured.
+pub trait RasterFont: FontMetrics {
+    /// Draws the glyph for c with the pen at (x, y), the top of the line,
+    /// tinted and alpha blended. Missing characters are drawn as '?'.
+    fn draw_glyph(&self, framebuffer: &mut Framebuffer, c: char, x: f32, y: f32, font_size: f32, tint: Rgba);
+}
+
+impl<F: RasterFont + ?Sized> RasterFont for &F {
+    fn draw_glyph(&self, framebuffer: &mut Framebuffer, c: char, x: f32, y: f32, font_size: f32, tint: Rgba) {
+        (**self).draw_glyph(framebuffer, c, x, y, font_size, tint);
+    }
+}
+
+impl<F: RasterFont + ?Sized> RasterFont for Box<F> {
+    fn draw_glyph(&self, framebuffer: &mut Framebuffer, c: char, x: f32, y: f32, font_size: f32, tint: Rgba) {
+        (**self).draw_glyph(framebuffer, c, x, y, font_size, tint);
+    }
+}
+
+// The way raylib's DrawTextEx() draws an image font on the GPU: the glyph
+// rectangle is scaled by font_size / base_size, sampled with point filtering
+// at each pixel center, tinted, then alpha blended.
+
+impl RasterFont for BitmapFont {
+    fn draw_glyph(&self, framebuffer: &mut Framebuffer, c: char, x: f32, y: f32, font_size: f32, tint: Rgba) {
+        let rect = match self.glyph(c) {
+            Some(rect) => *rect,
+            None => return,
+        };
+        let scale = font_size / self.base_size() as f32;
+        let dst_width = rect.width as f32 * scale;
+        let dst_height = rect.height as f32 * scale;
+
+        let x_start = x.floor().max(0.0) as usize;
+        let y_start = y.floor().max(0.0) as usize;
+        let x_end = ((x + dst_width).ceil().max(0.0) as usize).min(framebuffer.width);
+        let y_end = ((y + dst_height).ceil().max(0.0) as usize).min(framebuffer.height);
+
+        for py in y_start..y_end {
+            let v = ((py as f32 + 0.5 - y) / scale).floor();
+            if v < 0.0 || v >= rect.height as f32 {
+                continue;
+            }
+            for px in x_start..x_end {
+                let u = ((px as f32 + 0.5 - x) / scale).floor();
+                if u < 0.0 || u >= rect.width as f32 {
+                    continue;
+                }
+
+                let src = self.pixel(rect.x + u as usize, rect.y + v as usize);
+                let src = [
+                    mul_u8(src[0], tint.r),
+                    mul_u8(src[1], tint.g),
+                    mul_u8(src[2], tint.b),
+                    mul_u8(src[3], tint.a),
+                ];
+                framebuffer.blend(px, py, src);
+            }
+        }
+    }
+}
+
+/// Draws the laid out rangoli into a new framebuffer, at the moment of the
+/// animation the animator is at. Animator::still() gives the static pattern.
+#[allow(clippy::too_many_arguments)]
+pub fn rasterize<F: RasterFont + ?Sized>(
+    rangoli_disp: &[Vec<AlphaToDisplay>],
+    font: &F,
+    font_size: f32,
+    width: usize,
+    height: usize,
+    palette: &Palette,
+    color_mode: ColorMode,
+    animator: &Animator,
+) -> Framebuffer {
+    let mut fb = Framebuffer::new(width, height, palette.background);
+    rasterize_into(&mut fb, rangoli_disp, font, font_size, palette, color_mode, animator);
+    fb
+}
+
+/// Like rasterize(), except that the frame is drawn over an existing
+/// framebuffer, cleared first, so frame after frame reuses the same memory.
+pub fn rasterize_into<F: RasterFont + ?Sized>(
+    fb: &mut Framebuffer,
+    rangoli_disp: &[Vec<AlphaToDisplay>],
+    font: &F,
+    font_size: f32,
+    palette: &Palette,
+    color_mode: ColorMode,
+    animator: &Animator,
+) {
+    fb.clear(palette.background);
+    let rings = layout::ring_count(rangoli_disp);
+
+    for r_line in rangoli_disp {
+        for r_char in r_line {
+            let tint = animator.glyph_color(palette, color_mode, r_char, rings);
+            if tint.a == 0 {
+                continue;
+            }
+            fb.draw_text(font, &r_char.alpha, r_char.x, r_char.y, font_size, tint);
+        }
+    }
+}
+
+/// Draws every scene into a new framebuffer the size of the screen the
+/// scene was laid out for, kept until the next one.
+pub struct FramebufferRenderer<'f, F: RasterFont + ?Sized = BitmapFont> {
+    font: &'f F,
+    framebuffer: Option<Framebuffer>,
+}
+
+impl<'f, F: RasterFont + ?Sized> FramebufferRenderer<'f, F> {
+    pub fn new(font: &'f F) -> Self {
+        Self { font, framebuffer: None }
+    }
+
+    /// The last frame drawn, None before the first.
+    pub fn framebuffer(&self) -> Option<&Framebuffer> {
+        self.framebuffer.as_ref()
+    }
+
+    pub fn into_framebuffer(self) -> Option<Framebuffer> {
+        self.framebuffer
+    }
+}
+
+impl<'f, F: RasterFont + ?Sized> RangoliRenderer for FramebufferRenderer<'f, F> {
+    fn render(&mut self, scene: &Scene) -> Result<(), RangoliError> {
+        let layout = &scene.glyph_layout.layout;
+        self.framebuffer = Some(rasterize(
+            &scene.glyph_layout.glyphs,
+            self.font,
+            layout.font_size,
+            layout.screen_width as usize,
+            layout.screen_height as usize,
+            scene.palette,
+            scene.color_mode,
+            scene.animator,
+        ));
+        Ok(())
+    }
+}
+
+pub(crate) fn mul_u8(a: u8, b: u8) -> u8 {
+    ((a as u32 * b as u32 + 127) / 255) as u8
+}
+
+pub(crate) fn to_io_error(e: png::EncodingError) -> io::Error {
+    match e {
+        png::EncodingError::IoError(e) => e,
+        e => io::Error::other(e),
+    }
+}
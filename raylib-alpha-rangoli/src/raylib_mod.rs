@@ -1,69 +1,81 @@
+use std::path::Path;
+
 use raylib::prelude::*;
-use std::collections::HashMap;
-use std::process;
 
-use crate::rangoli::{AlphabetSet, RangoliTextPattern};
+use crate::animation::{Animator, DEFAULT_FPS};
+use crate::error::RangoliError;
+use crate::fallback::FontChain;
+use crate::fonts;
+use crate::layout::{self, GlyphLayout, Spacing};
+use crate::metrics::FontMetrics;
+use crate::palette::{ColorMode, Palette, Rgba};
+use crate::render::{RangoliRenderer, Scene};
+use crate::rangoli::{AlphabetSet, PatternShape, RangoliTextPattern, LOWER_BOUND};
+use crate::transform::Transform;
 
-pub const SCREEN_WIDTH: i32 = 1280;
-pub const SCREEN_HEIGHT: i32 = 1024;
-pub const DEFAULT_FPS: u32 = 24;
-pub const FONT_SIZE: f32 = 18.0;
+const HELP_FONT_SIZE: i32 = 10;
+const HELP_TEXT: &str = "[Up/Down] n  [S] shape  [F] font  [G] spacing  [P] palette  [M] color mode  [C] case  [R] replay";
 
-const ALPHA_WIDTH_PAD: f32 = 3.0;
-const ALPHA_HEIGHT_PAD: f32 = 18.5;
-const X_OFFSET_THRESHOLD: f32 = 2.0;
-const TOP_OFFSET: f32 = 40.0;
+// Fonts loaded by raylib are measured by raylib itself, so the layout matches
+// what draw_text_ex() draws.
 
-#[derive(Debug, Clone, Copy)]
-struct AlphaToDisplay {
-    alpha: char,
-    coord: Vector2,
-}
+impl FontMetrics for Font {
+    fn base_size(&self) -> f32 {
+        RaylibFont::base_size(self) as f32
+    }
 
-impl AlphaToDisplay {
-    fn new(
-        c: char,
-        line_index: usize,
-        mid_index: usize,
-        char_index: usize,
-        alpha_offsets: &HashMap<char, f32>,
-        max_alpha_offset: f32,
-    ) -> Self {
-        let mut alpha_coord = Vector2::default();
-
-        // For narrow letters such as 'i', 'j' or 't', fudge their x-coordinate
-        // to display the glyph closer to the center of their display cell,
-        // at the least by X_OFFSET_THRESHOLD. This is purely for aesthetic effect
-        // and may not work well for all fonts.
-
-        let x_offset = alpha_offsets.get(&c);
-
-        if let Some(val) = x_offset {
-            if char_index == mid_index {
-                alpha_coord.x = (SCREEN_WIDTH as f32 / 2.0);
-            } else if char_index > mid_index {
-                alpha_coord.x = (SCREEN_WIDTH as f32 / 2.0)
-                    + ((char_index - mid_index) as f32 * (max_alpha_offset + 2.0))
-                    + ((char_index - mid_index) as f32 * ALPHA_WIDTH_PAD);
-            } else {
-                alpha_coord.x = (SCREEN_WIDTH as f32 / 2.0)
-                    - ((mid_index - char_index) as f32 * (max_alpha_offset + 2.0))
-                    - ((mid_index - char_index) as f32 * ALPHA_WIDTH_PAD);
-            }
+    fn has_glyph(&self, c: char) -> bool {
+        self.chars().iter().any(|glyph| glyph.value == c as i32)
+    }
 
-            if *val <= X_OFFSET_THRESHOLD {
-                alpha_coord.x += X_OFFSET_THRESHOLD;
-            }
+    fn advance(&self, c: char) -> f32 {
+        RaylibFont::measure_text(self, &c.to_string(), RaylibFont::base_size(self) as f32, 0.0).x
+    }
+
+    // Raylib keeps the image of every glyph, drawn offsetX pixels past the pen.
+    fn ink_bounds(&self, c: char) -> Option<(f32, f32)> {
+        let glyphs = self.chars();
+        let glyph = glyphs
+            .iter()
+            .find(|glyph| glyph.value == c as i32)
+            .or_else(|| glyphs.iter().find(|glyph| glyph.value == '?' as i32))?;
+
+        if glyph.image.width > 0 {
+            Some((glyph.offsetX as f32, (glyph.offsetX + glyph.image.width) as f32))
         } else {
-            panic!("Error: alpha_offset.get(&c) returned None!");
+            None
         }
+    }
 
-        alpha_coord.y = TOP_OFFSET + (ALPHA_HEIGHT_PAD * (line_index as f32));
+    fn measure_text(&self, text: &str, font_size: f32, spacing: f32) -> f32 {
+        RaylibFont::measure_text(self, text, font_size, spacing).x
+    }
+}
 
-        Self {
-            alpha: c,
-            coord: alpha_coord,
-        }
+/// Loads a font file with raylib. TrueType and OpenType fonts are rasterized
+/// at font_size with a glyph for every character of codepoints, since raylib
+/// only loads the first 95 ASCII characters by default. Image fonts and
+/// BMFonts keep the size they were drawn at.
+pub fn load_font(
+    rl: &mut RaylibHandle,
+    thread: &RaylibThread,
+    path: &Path,
+    font_size: usize,
+    codepoints: &str,
+) -> Result<Font, RangoliError> {
+    let filename = path.to_string_lossy();
+    let font = if fonts::is_truetype_font(path) {
+        rl.load_font_ex(thread, &filename, font_size as i32, Some(codepoints))
+    } else {
+        rl.load_font(thread, &filename)
+    };
+
+    font.map_err(|e| RangoliError::Font(format!("Couldn't load font {}: {}", path.display(), e)))
+}
+
+impl From<Rgba> for Color {
+    fn from(rgba: Rgba) -> Self {
+        Color::new(rgba.r, rgba.g, rgba.b, rgba.a)
     }
 }
 
@@ -71,103 +83,181 @@ pub struct RLDriver<'p> {
     rl: &'p mut RaylibHandle,
     thread: &'p RaylibThread,
     fps: u32,
-    font: &'p Font,
-    // Generated text pattern owned by the rangoli module.
-    rangoli_text: &'p RangoliTextPattern,
-    // Glyph representation of the rangoli pattern to display.
-    rangoli_disp: Vec<Vec<AlphaToDisplay>>,
-    // X-offset of a character of the given font set.
-    alpha_offsets: HashMap<char, f32>,
+    // Loaded fonts to cycle through, and the one in use.
+    fonts: Vec<Font>,
+    font_index: usize,
+    // Fill in the glyphs the font in use lacks, tried in order.
+    fallback_fonts: Vec<Font>,
+    // Fixed size to draw glyphs at, or None to fit the window.
+    font_size: Option<f32>,
+    // Size of the rangoli, from LOWER_BOUND up to the length of the alphabet.
+    n: i32,
     // Range of tokens, typically a-z or A-Z
-    alphabet_set: &'p AlphabetSet,
+    alphabet_set: AlphabetSet,
+    shape: PatternShape,
+    // Applied in order every time the pattern is generated.
+    transforms: Vec<Transform>,
+    spacing: Spacing,
+    // Generated text pattern owned by the rangoli module.
+    rangoli_text: RangoliTextPattern,
+    // Glyphs of the rangoli pattern, fitted to the current window size.
+    glyph_layout: GlyphLayout,
+    palettes: Vec<Palette>,
+    palette_index: usize,
+    color_mode: ColorMode,
+    animator: Animator,
 }
 
 impl<'p> RLDriver<'p> {
+    /// The driver owns everything that can change while the window is open,
+    /// so the pattern can be regenerated live from keyboard input. The first
+    /// font is the one drawn initially. Fails without a font or when n does
+    /// not fit the alphabet.
+    #[allow(clippy::too_many_arguments)]
     pub fn build(
         rl: &'p mut RaylibHandle,
         thread: &'p RaylibThread,
-        font: &'p Font,
-        rangoli_text: &'p mut RangoliTextPattern,
-        alphabet_set: &'p AlphabetSet,
-    ) -> RLDriver<'p> {
-        let (max_alpha_offset, alpha_offsets) = RLDriver::calc_alpha_offsets(&rl, alphabet_set);
-        let (rangoli_pattern, _) = rangoli_text.get_rangoli_text();
-
-        let mut alpha_display = rangoli_pattern.iter()
-            .enumerate()
-            .map(|(line_index, r_line)| {
-            // The middle token is always the first in the alphabet, 'a' for example.
-            // It is the pivot of the range of tokens incrementing to the left 
-            // and right respectively: i.e. ["a", "b-a-b", "c-b-a-b-c"] 
-            // for an n=3 rangoli pattern.
-
-            let mid_index: usize = r_line.chars().count() / 2;
-
-            let mut inner_vec = (0..r_line.chars().count())
-                .filter(|char_index| char_index % 2 == 0 )
-                .map(|char_index| {
-                // A line in a rangoli pattern always has an odd number of tokens,
-                // Odd indices always refer to the delimiter character, 
-                // '-' in "c-b-a-b-c" for example, which is skipped over.
- 
-                let alpha_char = r_line.chars().nth(char_index);
-                if let Some(c) = alpha_char {
-                    AlphaToDisplay::new(
-                            c,
-                            line_index,
-                            mid_index,
-                            char_index,
-                            &alpha_offsets,
-                            max_alpha_offset,
-                    )
-                } else {
-                    panic!("Error: r_line.chars().nth(char_index) yielded None!");
-                }
-            }).collect::<Vec<AlphaToDisplay>>();
+        fonts: Vec<Font>,
+        fallback_fonts: Vec<Font>,
+        n: i32,
+        alphabet_set: AlphabetSet,
+        shape: PatternShape,
+        transforms: Vec<Transform>,
+        spacing: Spacing,
+        font_size: Option<f32>,
+        palette: Palette,
+        color_mode: ColorMode,
+        animator: Animator,
+    ) -> Result<RLDriver<'p>, RangoliError> {
+        let mut palettes = Palette::all();
+        let palette_index = match palettes.iter().position(|p| *p == palette) {
+            Some(index) => index,
+            None => {
+                palettes.insert(0, palette);
+                0
+            }
+        };
 
-            inner_vec
-        }).collect::<Vec<Vec<AlphaToDisplay>>>();
+        let font = fonts.first().ok_or_else(|| RangoliError::Font(String::from("No font to draw with")))?;
+        let rangoli_text = RangoliTextPattern::with_shape(n, &alphabet_set, shape)?.transform(&transforms)?;
+        let font_chain = FontChain::new(font, fallback_fonts.iter().collect());
+        let glyph_layout = RLDriver::layout(rl, &font_chain, &rangoli_text, spacing, font_size);
 
-        RLDriver {
+        Ok(RLDriver {
             rl,
             thread,
-            font,
             fps: DEFAULT_FPS,
+            fonts,
+            font_index: 0,
+            fallback_fonts,
+            font_size,
+            n,
+            alphabet_set,
+            shape,
+            transforms,
+            spacing,
             rangoli_text,
-            rangoli_disp: alpha_display,
-            alpha_offsets,
-            alphabet_set
-        }
+            glyph_layout,
+            palettes,
+            palette_index,
+            color_mode,
+            animator,
+        })
     }
 
-    fn calc_alpha_offsets(rl: &RaylibHandle, alphabet_set: &AlphabetSet) -> (f32, HashMap<char, f32>) {
-        let mut ret_val: HashMap<char, f32> = HashMap::new();
-        let mut max_alpha_offset: f32 = 0.0;
+    // Runs the layout stage for the current window size, measuring glyphs
+    // with the fonts that draw them.
 
-        // A temporary variable is required for a char conversion because
-        // a char in Rust is a 4 byte UTF8, unlike ASCII.
+    fn layout(
+        rl: &RaylibHandle,
+        font_chain: &FontChain<&Font>,
+        rangoli_text: &RangoliTextPattern,
+        spacing: Spacing,
+        font_size: Option<f32>,
+    ) -> GlyphLayout {
+        let (screen_width, screen_height) = (rl.get_screen_width() as f32, rl.get_screen_height() as f32);
+        layout::layout_rangoli(rangoli_text, font_chain, spacing, font_size, screen_width, screen_height)
+    }
 
-        let mut tmp = [0u8; 4];
-        for i in 0..alphabet_set.get_alphabet().len() {
-            let tok: char;
-            let c = alphabet_set.get_alphabet().get(i);
+    /// Generates the text pattern again from a new n, alphabet and shape,
+    /// which are only kept when it succeeds. A transformation can need more
+    /// of its own alphabet than n allows, such as transliterating to the ten
+    /// digits, in which case the pattern on screen is left as is.
+    fn regenerate(&mut self, n: i32, alphabet_set: AlphabetSet, shape: PatternShape) {
+        let rangoli_text = match RangoliTextPattern::with_shape(n, &alphabet_set, shape)
+            .and_then(|rangoli_text| rangoli_text.transform(&self.transforms))
+        {
+            Ok(rangoli_text) => rangoli_text,
+            Err(_) => return,
+        };
 
-            if let Some(token) = c {
-                tok = *token
-            } else {
-                tok = alphabet_set.get_false_token()
-            }
+        self.n = n;
+        self.alphabet_set = alphabet_set;
+        self.shape = shape;
+        self.rangoli_text = rangoli_text;
+        self.relayout();
+        self.animator.reset();
+    }
 
-            let mut x_offset = (rl
-                .measure_text(tok.encode_utf8(&mut tmp), FONT_SIZE as i32) / 2) as f32;
-            ret_val.insert(tok, x_offset);
+    /// Fits the pattern to the current window size again.
+    fn relayout(&mut self) {
+        let font_chain = FontChain::new(&self.fonts[self.font_index], self.fallback_fonts.iter().collect());
+        self.glyph_layout = RLDriver::layout(self.rl, &font_chain, &self.rangoli_text, self.spacing, self.font_size);
+    }
 
-            if x_offset > max_alpha_offset {
-                max_alpha_offset = x_offset;
-            }
+    fn handle_input(&mut self) {
+        let max_n = self.alphabet_set.len() as i32;
+
+        if self.rl.is_key_pressed(KeyboardKey::KEY_UP) && self.n < max_n {
+            self.regenerate(self.n + 1, self.alphabet_set.clone(), self.shape);
+        }
+        if self.rl.is_key_pressed(KeyboardKey::KEY_DOWN) && self.n > LOWER_BOUND {
+            self.regenerate(self.n - 1, self.alphabet_set.clone(), self.shape);
         }
+        if self.rl.is_key_pressed(KeyboardKey::KEY_C) {
+            // Switch the whole alphabet to the other case. Scripts without
+            // letter case, such as digits or hiragana, are left unchanged.
 
-        (max_alpha_offset, ret_val)
+            let alphabet_set = if self.alphabet_set.get_alphabet().iter().any(|t| t.chars().any(char::is_lowercase)) {
+                self.alphabet_set.to_uppercase()
+            } else {
+                self.alphabet_set.to_lowercase()
+            };
+            self.regenerate(self.n, alphabet_set, self.shape);
+        }
+        if self.rl.is_key_pressed(KeyboardKey::KEY_S) {
+            let shape = match self.shape {
+                PatternShape::Diamond => PatternShape::Square,
+                PatternShape::Square => PatternShape::Hexagonal,
+                PatternShape::Hexagonal => PatternShape::Circular,
+                PatternShape::Circular => PatternShape::Diamond,
+            };
+            self.regenerate(self.n, self.alphabet_set.clone(), shape);
+        }
+        if self.rl.is_key_pressed(KeyboardKey::KEY_F) && !self.fonts.is_empty() {
+            self.font_index = (self.font_index + 1) % self.fonts.len();
+            self.relayout();
+        }
+        if self.rl.is_key_pressed(KeyboardKey::KEY_G) {
+            self.spacing = match self.spacing {
+                Spacing::Grid => Spacing::Proportional,
+                Spacing::Proportional => Spacing::Grid,
+            };
+            self.relayout();
+        }
+        if self.rl.is_key_pressed(KeyboardKey::KEY_P) {
+            self.palette_index = (self.palette_index + 1) % self.palettes.len();
+        }
+        if self.rl.is_key_pressed(KeyboardKey::KEY_M) {
+            self.color_mode = match self.color_mode {
+                ColorMode::Ring => ColorMode::Letter,
+                ColorMode::Letter => ColorMode::Alternate,
+                ColorMode::Alternate => ColorMode::Ring,
+            };
+        }
+        if self.rl.is_key_pressed(KeyboardKey::KEY_R) {
+            self.animator.reset();
+        }
     }
 
     pub fn run(&mut self) {
@@ -176,28 +266,80 @@ impl<'p> RLDriver<'p> {
             // Raylib logic block.
             // *******************
 
+            self.handle_input();
+
+            if self.rl.is_window_resized() {
+                self.relayout();
+            }
+
+            // The animation advances by one frame at the target frame rate,
+            // so its pace does not depend on how long drawing takes.
+
+            self.animator.update(1.0 / self.fps as f32);
+
             // ******************
             // Raylib draw block.
             // ******************
 
-            let mut d = self.rl.begin_drawing(&self.thread);
+            let palette = &self.palettes[self.palette_index];
+            let font_chain = FontChain::new(&self.fonts[self.font_index], self.fallback_fonts.iter().collect());
+            let screen_height = self.rl.get_screen_height();
+            let scene = Scene::new(&self.rangoli_text, &self.glyph_layout, palette, self.color_mode, &self.animator);
+            let mut d = self.rl.begin_drawing(self.thread);
+
+            // Drawing to the window cannot fail.
+            let _ = RaylibRenderer::new(&mut d, &font_chain).render(&scene);
+
+            let status = format!("n: {}  palette: {}  {}", self.n, palette.name, HELP_TEXT);
+            d.draw_text(
+                &status,
+                HELP_FONT_SIZE,
+                screen_height - 2 * HELP_FONT_SIZE,
+                HELP_FONT_SIZE,
+                Color::from(palette.stops.first().copied().unwrap_or(palette.background)),
+            );
+        }
+    }
+}
+
+/// Draws scenes with Raylib, onto the window or any other draw target such
+/// as a render texture. Each token is drawn in runs of the first font of the
+/// chain that has their glyphs.
+pub struct RaylibRenderer<'d, D: RaylibDraw> {
+    d: &'d mut D,
+    font_chain: &'d FontChain<&'d Font>,
+}
+
+impl<'d, D: RaylibDraw> RaylibRenderer<'d, D> {
+    pub fn new(d: &'d mut D, font_chain: &'d FontChain<&'d Font>) -> Self {
+        Self { d, font_chain }
+    }
+}
+
+impl<'d, D: RaylibDraw> RangoliRenderer for RaylibRenderer<'d, D> {
+    fn render(&mut self, scene: &Scene) -> Result<(), RangoliError> {
+        self.d.clear_background(Color::from(scene.palette.background));
+
+        for r_line in &scene.glyph_layout.glyphs {
+            for r_char in r_line {
+                let color = scene.glyph_color(r_char);
+                if color.a == 0 {
+                    continue;
+                }
 
-            d.clear_background(Color::SADDLEBROWN);
+                // Runs follow each other the way glyphs do within one
+                // draw_text_ex(), one spacing apart.
 
-            for r_line in &self.rangoli_disp {
-                for r_char in r_line {
-                    let mut tmp = [0u8; 4];
+                let font_size = scene.glyph_layout.layout.font_size;
+                let mut x = r_char.x;
 
-                    d.draw_text_ex(
-                        &self.font,
-                        r_char.alpha.encode_utf8(&mut tmp),
-                        r_char.coord,
-                        FONT_SIZE,
-                        1.0,
-                        Color::DARKKHAKI,
-                    );
+                for (index, run) in self.font_chain.runs(&r_char.alpha) {
+                    let font = self.font_chain.fonts()[index];
+                    self.d.draw_text_ex(font, run, Vector2::new(x, r_char.y), font_size, 1.0, Color::from(color));
+                    x += FontMetrics::measure_text(font, run, font_size, 1.0) + 1.0;
                 }
             }
         }
+        Ok(())
     }
 }
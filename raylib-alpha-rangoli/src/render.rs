@@ -0,0 +1,65 @@
+use crate::animation::Animator;
+use crate::error::RangoliError;
+use crate::layout::{self, AlphaToDisplay, GlyphLayout};
+use crate::palette::{ColorMode, Palette, Rgba};
+use crate::rangoli::{Cell, RangoliTextPattern};
+
+/// One frame to draw: the pattern, its glyphs as placed by the layout stage,
+/// and how to color them at the animator's current moment. A scene holds no
+/// backend state, so the same one can be handed to any renderer.
+pub struct Scene<'a> {
+    pub rangoli: &'a RangoliTextPattern,
+    pub glyph_layout: &'a GlyphLayout,
+    pub palette: &'a Palette,
+    pub color_mode: ColorMode,
+    pub animator: &'a Animator,
+    rings: usize,
+}
+
+impl<'a> Scene<'a> {
+    pub fn new(
+        rangoli: &'a RangoliTextPattern,
+        glyph_layout: &'a GlyphLayout,
+        palette: &'a Palette,
+        color_mode: ColorMode,
+        animator: &'a Animator,
+    ) -> Self {
+        Self {
+            rangoli,
+            glyph_layout,
+            palette,
+            color_mode,
+            animator,
+            rings: layout::ring_count(&glyph_layout.glyphs),
+        }
+    }
+
+    /// Number of rings, the center token included.
+    pub fn rings(&self) -> usize {
+        self.rings
+    }
+
+    /// Color of a glyph right now. Fully transparent while its ring is hidden.
+    pub fn glyph_color(&self, glyph: &AlphaToDisplay) -> Rgba {
+        self.animator.glyph_color(self.palette, self.color_mode, glyph, self.rings)
+    }
+
+    /// Color of a cell of the text grid right now, the way glyph_color()
+    /// colors the glyph drawn for it.
+    pub fn cell_color(&self, cell: &Cell) -> Rgba {
+        self.animator.color_at(self.palette, self.color_mode, cell.ring, cell.token, self.rings)
+    }
+
+    /// Whether any of a ring shows right now.
+    pub fn is_ring_shown(&self, ring: usize) -> bool {
+        self.animator.ring_alpha(ring, self.rings) > 0.0
+    }
+}
+
+/// A backend that draws scenes: the Raylib window, SVG, the terminal or a
+/// framebuffer in memory. Layout happens before, in the layout module, so a
+/// new output format only needs to implement this.
+pub trait RangoliRenderer {
+    /// Draws one frame.
+    fn render(&mut self, scene: &Scene) -> Result<(), RangoliError>;
+}
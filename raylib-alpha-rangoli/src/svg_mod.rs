@@ -0,0 +1,139 @@
+use std::fmt::Write as _;
+use std::io;
+
+use crate::error::RangoliError;
+use crate::render::{RangoliRenderer, Scene};
+
+/// Writes every scene as a standalone SVG document. The page size, font
+/// size and colors all come from the scene.
+pub struct SvgRenderer<W: io::Write> {
+    writer: W,
+    font_family: String,
+}
+
+impl<W: io::Write> SvgRenderer<W> {
+    pub fn new(writer: W, font_family: &str) -> Self {
+        Self { writer, font_family: font_family.to_string() }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: io::Write> RangoliRenderer for SvgRenderer<W> {
+    fn render(&mut self, scene: &Scene) -> Result<(), RangoliError> {
+        let layout = &scene.glyph_layout.layout;
+        let mut svg = String::new();
+
+        // Writing to a String never fails, hence the ignored results below.
+
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = layout.screen_width as i32,
+            h = layout.screen_height as i32
+        );
+        let _ = writeln!(svg, r#"  <rect width="100%" height="100%" fill="{}"/>"#, scene.palette.background.to_hex());
+
+        // Raylib positions a glyph by its top-left corner, while SVG text sits on
+        // its baseline by default. Hanging the text from its y-coordinate keeps
+        // both outputs aligned.
+
+        let _ = writeln!(svg, r#"  <g font-family="{}" font-size="{}">"#, escape_xml(&self.font_family), layout.font_size);
+
+        // Glyphs that are fully transparent are left out.
+
+        for r_char in scene.glyph_layout.glyphs.iter().flatten() {
+            let color = scene.glyph_color(r_char);
+            if color.a == 0 {
+                continue;
+            }
+            let _ = writeln!(
+                svg,
+                r#"    <text x="{}" y="{}" fill="{}" dominant-baseline="hanging">{}</text>"#,
+                r_char.x,
+                r_char.y,
+                color.to_hex(),
+                escape_xml(&r_char.alpha)
+            );
+        }
+
+        svg.push_str("  </g>\n</svg>\n");
+        self.writer.write_all(svg.as_bytes())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{AnimationMode, Animator};
+    use crate::layout::{self, Spacing};
+    use crate::metrics::Monospace;
+    use crate::palette::{ColorMode, Palette};
+    use crate::rangoli::{AlphabetSet, RangoliTextPattern};
+
+    #[test]
+    fn test_svg_renderer() {
+        let alphabet_set = AlphabetSet::from_token_str("< &").unwrap();
+        let rtp_02 = RangoliTextPattern::new(2, &alphabet_set).unwrap();
+        let glyph_layout = layout::layout_rangoli(&rtp_02, &Monospace, Spacing::Grid, None, 800.0, 600.0);
+        let palette = Palette::default();
+
+        let still = Animator::still();
+        let mut renderer = SvgRenderer::new(Vec::new(), "monospace");
+        renderer.render(&Scene::new(&rtp_02, &glyph_layout, &palette, ColorMode::Ring, &still)).unwrap();
+        let svg = String::from_utf8(renderer.into_inner()).unwrap();
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="800" height="600""#));
+        assert!(svg.contains(r##"fill="#8b4513""##));
+        assert!(svg.contains(&format!(r#"<g font-family="monospace" font-size="{}">"#, glyph_layout.layout.font_size)));
+        assert_eq!(5, svg.matches(r##"fill="#bdb76b""##).count());
+        assert_eq!(5, svg.matches("<text ").count());
+        assert_eq!(1, svg.matches(">&lt;</text>").count());
+        assert_eq!(4, svg.matches(">&amp;</text>").count());
+
+        // A reveal that has only just started leaves out all but the center.
+
+        let reveal = Animator::new(AnimationMode::Reveal, 1.0, false);
+        let mut renderer = SvgRenderer::new(Vec::new(), "monospace");
+        renderer.render(&Scene::new(&rtp_02, &glyph_layout, &palette, ColorMode::Ring, &reveal)).unwrap();
+        assert_eq!(1, String::from_utf8(renderer.into_inner()).unwrap().matches("<text ").count());
+    }
+
+    // Takes every write, then fails to flush, like a full disk behind a
+    // buffered file.
+
+    struct FailingFlush;
+
+    impl io::Write for FailingFlush {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Err(io::Error::new(io::ErrorKind::StorageFull, "no space left"))
+        }
+    }
+
+    #[test]
+    fn test_svg_renderer_flush_error() {
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+        let rtp_02 = RangoliTextPattern::new(2, &alphabet_set).unwrap();
+        let glyph_layout = layout::layout_rangoli(&rtp_02, &Monospace, Spacing::Grid, None, 800.0, 600.0);
+        let palette = Palette::default();
+        let still = Animator::still();
+
+        let mut renderer = SvgRenderer::new(FailingFlush, "monospace");
+        let result = renderer.render(&Scene::new(&rtp_02, &glyph_layout, &palette, ColorMode::Ring, &still));
+        assert!(matches!(result, Err(RangoliError::Io(e)) if e.kind() == io::ErrorKind::StorageFull));
+    }
+}
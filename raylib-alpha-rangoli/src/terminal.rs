@@ -0,0 +1,438 @@
+use std::fmt::Write as _;
+use std::io::Write;
+use std::{thread, time::Duration};
+
+use crate::animation::Animator;
+use crate::error::RangoliError;
+use crate::layout::GlyphLayout;
+use crate::palette::{ColorMode, Palette, Rgba};
+use crate::rangoli::{line_span, Cell, RangoliTextPattern};
+use crate::render::{RangoliRenderer, Scene};
+
+// How far a delimiter's color is taken from the background towards the color
+// of its ring, so the tokens stand out.
+const DELIMITER_MIX: f32 = 0.5;
+// How far a ring fill is taken from the background towards the ring's color,
+// light enough for the glyphs on it to stay readable.
+const RING_FILL_MIX: f32 = 0.3;
+
+/// How many colors a terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    // 24-bit colors, exactly as in the palette.
+    TrueColor,
+    // The xterm palette of 256 colors: a 6x6x6 color cube and a gray ramp.
+    Ansi256,
+    // Plain text, without any escape sequence for colors.
+    Mono,
+}
+
+impl ColorDepth {
+    pub const NAMES: [&'static str; 3] = ["truecolor", "256", "mono"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "truecolor" | "24bit" => Some(ColorDepth::TrueColor),
+            "256" => Some(ColorDepth::Ansi256),
+            "mono" => Some(ColorDepth::Mono),
+            _ => None,
+        }
+    }
+
+    /// Guesses what the terminal supports from the environment, the way most
+    /// command line tools do. See detect_with().
+    pub fn detect() -> Self {
+        Self::detect_with(|key| std::env::var(key).ok())
+    }
+
+    /// A non-empty NO_COLOR turns colors off and COLORTERM announces 24-bit
+    /// colors. Otherwise any TERM but "dumb" is taken to have 256 colors.
+    pub fn detect_with<F: Fn(&str) -> Option<String>>(var: F) -> Self {
+        if var("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            return ColorDepth::Mono;
+        }
+        if matches!(var("COLORTERM").as_deref(), Some("truecolor" | "24bit")) {
+            return ColorDepth::TrueColor;
+        }
+        match var("TERM").as_deref() {
+            None | Some("" | "dumb") => ColorDepth::Mono,
+            Some(_) => ColorDepth::Ansi256,
+        }
+    }
+
+    // The escape sequence that sets the foreground (38) or background (48)
+    // color.
+
+    fn sgr(&self, layer: u8, color: Rgba) -> String {
+        match self {
+            ColorDepth::TrueColor => format!("\x1b[{};2;{};{};{}m", layer, color.r, color.g, color.b),
+            ColorDepth::Ansi256 => format!("\x1b[{};5;{}m", layer, ansi256(color)),
+            ColorDepth::Mono => String::new(),
+        }
+    }
+}
+
+/// Index of the closest color of the xterm 256-color palette, either in its
+/// 6x6x6 color cube (16 to 231) or on its gray ramp (232 to 255). The 16
+/// system colors are left out, as terminals theme them.
+pub fn ansi256(color: Rgba) -> u8 {
+    const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+    let cube_index = |v: u8| match v {
+        0..=47 => 0,
+        48..=114 => 1,
+        v => (v as usize - 35) / 40,
+    };
+    let distance = |r: u8, g: u8, b: u8| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, color.r) + d(g, color.g) + d(b, color.b)
+    };
+
+    let (ri, gi, bi) = (cube_index(color.r), cube_index(color.g), cube_index(color.b));
+    let cube_distance = distance(CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+
+    let average = (color.r as usize + color.g as usize + color.b as usize) / 3;
+    let gray_index = (average.saturating_sub(3) / 10).min(23);
+    let gray = (8 + 10 * gray_index) as u8;
+
+    if distance(gray, gray, gray) < cube_distance {
+        232 + gray_index as u8
+    } else {
+        16 + (36 * ri + 6 * gi + bi) as u8
+    }
+}
+
+// The cells of a row the scene shows, with hidden rings as padding.
+
+fn shown_cells(scene: &Scene, cells: Vec<Option<Cell>>) -> Vec<Option<Cell>> {
+    cells.into_iter().map(|cell| cell.filter(|cell| scene.is_ring_shown(cell.ring))).collect()
+}
+
+/// Prints the pattern as text, one line per row of the grid. Glyph positions
+/// are of no use on a terminal, so it draws from the grid itself. Rings the
+/// animation has not revealed yet print as padding.
+pub struct TerminalRenderer<W: Write> {
+    writer: W,
+    // Padding character, or None to trim the lines.
+    fill: Option<char>,
+}
+
+impl<W: Write> TerminalRenderer<W> {
+    pub fn new(writer: W, fill: Option<char>) -> Self {
+        Self { writer, fill }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> RangoliRenderer for TerminalRenderer<W> {
+    fn render(&mut self, scene: &Scene) -> Result<(), RangoliError> {
+        let grid = scene.rangoli.get_grid();
+
+        let gap = self.fill.unwrap_or(grid.get_alphabet_set().get_false_token());
+
+        for cells in grid.iter_rows() {
+            // Hidden rings leave padding behind, which is trimmed like the rest.
+
+            let shown = shown_cells(scene, cells);
+            let mut line = String::new();
+            for col in line_span(&shown, self.fill) {
+                line.push_str(&grid.cell_text(col, shown[col].as_ref(), Some(gap)));
+            }
+            writeln!(self.writer, "{}", line)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Prints the pattern like TerminalRenderer, in the colors of the palette
+/// as ANSI escape sequences, and optionally on a tint of every ring's color.
+/// Each scene after the first is drawn over the one before, moving the
+/// cursor back up, so the scenes of an animation play in place. Output that
+/// is not a terminal has no cursor to move, see set_in_place().
+pub struct AnsiRenderer<W: Write> {
+    writer: W,
+    fill: Option<char>,
+    depth: ColorDepth,
+    ring_fill: bool,
+    in_place: bool,
+    // Lines of the last scene drawn, to move back up over.
+    lines_drawn: usize,
+}
+
+impl<W: Write> AnsiRenderer<W> {
+    pub fn new(writer: W, fill: Option<char>, depth: ColorDepth, ring_fill: bool) -> Self {
+        Self { writer, fill, depth, ring_fill, in_place: true, lines_drawn: 0 }
+    }
+
+    /// Whether scenes are drawn over the one before, the default. Without,
+    /// no cursor control sequences are written and each scene follows the
+    /// last, so play() draws only the final one.
+    pub fn set_in_place(&mut self, in_place: bool) {
+        self.in_place = in_place;
+    }
+
+    pub fn is_in_place(&self) -> bool {
+        self.in_place
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    // Foreground and background colors of a cell that shows. A fading ring is
+    // blended into the palette background, the only background known here.
+
+    fn cell_colors(&self, scene: &Scene, cell: &Cell) -> (Option<Rgba>, Option<Rgba>) {
+        if self.depth == ColorDepth::Mono {
+            return (None, None);
+        }
+
+        let background = scene.palette.background;
+        let color = scene.cell_color(cell);
+        let alpha = color.a as f32 / 255.0;
+        let foreground = match cell.is_delimiter {
+            true => background.lerp(color, alpha * DELIMITER_MIX),
+            false => background.lerp(color, alpha),
+        };
+
+        let fill = self.ring_fill.then(|| {
+            let ring_color = scene.animator.color_at(scene.palette, ColorMode::Ring, cell.ring, cell.token, scene.rings());
+            background.lerp(ring_color, RING_FILL_MIX * ring_color.a as f32 / 255.0)
+        });
+
+        (Some(foreground), fill)
+    }
+}
+
+impl<W: Write> RangoliRenderer for AnsiRenderer<W> {
+    fn render(&mut self, scene: &Scene) -> Result<(), RangoliError> {
+        let grid = scene.rangoli.get_grid();
+        let mut text = String::new();
+
+        // Writing to a String never fails, hence the ignored results below.
+
+        let redraw = self.in_place && self.lines_drawn > 0;
+        if redraw {
+            let _ = write!(text, "\x1b[{}A\r", self.lines_drawn);
+        }
+
+        let gap = self.fill.unwrap_or(grid.get_alphabet_set().get_false_token());
+
+        for cells in grid.iter_rows() {
+            // Trimmed like TerminalRenderer trims.
+
+            let shown = shown_cells(scene, cells);
+            let mut spans = Vec::new();
+            for col in line_span(&shown, self.fill) {
+                let (foreground, background) = match &shown[col] {
+                    Some(cell) => self.cell_colors(scene, cell),
+                    None => (None, None),
+                };
+                spans.push((grid.cell_text(col, shown[col].as_ref(), Some(gap)), foreground, background));
+            }
+
+            // Colors are only set when they change along the line.
+
+            let mut current = (None, None);
+            for (span, foreground, background) in spans {
+                if (foreground, background) != current {
+                    if current != (None, None) {
+                        text.push_str("\x1b[0m");
+                    }
+                    if let Some(foreground) = foreground {
+                        text.push_str(&self.depth.sgr(38, foreground));
+                    }
+                    if let Some(background) = background {
+                        text.push_str(&self.depth.sgr(48, background));
+                    }
+                    current = (foreground, background);
+                }
+                text.push_str(&span);
+            }
+            if current != (None, None) {
+                text.push_str("\x1b[0m");
+            }
+
+            // Redrawn lines can be shorter than the ones they cover.
+
+            if redraw {
+                text.push_str("\x1b[K");
+            }
+            text.push('\n');
+        }
+
+        self.writer.write_all(text.as_bytes())?;
+        self.writer.flush()?;
+        self.lines_drawn = grid.rows();
+        Ok(())
+    }
+}
+
+/// Plays the animation in the terminal, one scene every 1/fps seconds for
+/// the given duration in seconds, with the same frames a Capture would
+/// export. An infinite duration plays until the program is stopped. A
+/// renderer that does not draw in place gets the last frame only, at once.
+#[allow(clippy::too_many_arguments)]
+pub fn play<W: Write>(
+    renderer: &mut AnsiRenderer<W>,
+    rangoli: &RangoliTextPattern,
+    glyph_layout: &GlyphLayout,
+    palette: &Palette,
+    color_mode: ColorMode,
+    animator: &Animator,
+    fps: u32,
+    duration: f32,
+) -> Result<(), RangoliError> {
+    let fps = fps.max(1);
+    let frame_count = ((duration * fps as f32).round() as usize).max(1);
+    let first_frame = if renderer.is_in_place() { 0 } else { frame_count - 1 };
+    let mut animator = *animator;
+
+    for frame in first_frame..frame_count {
+        if frame > first_frame {
+            thread::sleep(Duration::from_secs_f32(1.0 / fps as f32));
+        }
+        animator.set_elapsed(frame as f32 / fps as f32);
+        renderer.render(&Scene::new(rangoli, glyph_layout, palette, color_mode, &animator))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{AnimationMode, Animator};
+    use crate::layout::{self, Spacing};
+    use crate::metrics::Monospace;
+    use crate::rangoli::AlphabetSet;
+    use crate::transform::Transform;
+
+    #[test]
+    fn test_terminal_renderer() {
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+        let rtp_03 = RangoliTextPattern::new(3, &alphabet_set).unwrap();
+        let glyph_layout = layout::layout_rangoli(&rtp_03, &Monospace, Spacing::Grid, None, 640.0, 480.0);
+        let palette = Palette::default();
+
+        let still = Animator::still();
+        let mut renderer = TerminalRenderer::new(Vec::new(), Some('-'));
+        renderer.render(&Scene::new(&rtp_03, &glyph_layout, &palette, ColorMode::Ring, &still)).unwrap();
+        assert_eq!(rtp_03.padded_lines('-').join("\n") + "\n", String::from_utf8(renderer.into_inner()).unwrap());
+
+        // Half way through the second ring of a reveal, the outer ring is
+        // still hidden.
+
+        let mut reveal = Animator::new(AnimationMode::Reveal, 1.0, false);
+        reveal.set_elapsed(1.5);
+        let mut renderer = TerminalRenderer::new(Vec::new(), Some('.'));
+        renderer.render(&Scene::new(&rtp_03, &glyph_layout, &palette, ColorMode::Ring, &reveal)).unwrap();
+        assert_eq!(".........\n....b....\n..b-a-b..\n....b....\n.........\n", String::from_utf8(renderer.into_inner()).unwrap());
+
+        // Without a fill the lines are trimmed at either end only, like the
+        // pattern's own, and the gaps of a checkerboard stay.
+
+        let checkerboard = rtp_03.transform(&[Transform::Rotate45]).unwrap();
+        let glyph_layout = layout::layout_rangoli(&checkerboard, &Monospace, Spacing::Grid, None, 640.0, 480.0);
+        let mut renderer = TerminalRenderer::new(Vec::new(), None);
+        renderer.render(&Scene::new(&checkerboard, &glyph_layout, &palette, ColorMode::Ring, &still)).unwrap();
+        assert_eq!(checkerboard.get_rangoli_text().0.join("\n") + "\n", String::from_utf8(renderer.into_inner()).unwrap());
+    }
+
+    #[test]
+    fn test_color_depth() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |key: &str| vars.iter().find(|(k, _)| *k == key).map(|(_, v)| v.to_string())
+        };
+
+        assert_eq!(ColorDepth::TrueColor, ColorDepth::detect_with(env(&[("COLORTERM", "truecolor"), ("TERM", "xterm")])));
+        assert_eq!(ColorDepth::Ansi256, ColorDepth::detect_with(env(&[("TERM", "xterm-256color")])));
+        assert_eq!(ColorDepth::Mono, ColorDepth::detect_with(env(&[("TERM", "dumb")])));
+        assert_eq!(ColorDepth::Mono, ColorDepth::detect_with(env(&[])));
+        assert_eq!(ColorDepth::Mono, ColorDepth::detect_with(env(&[("NO_COLOR", "1"), ("COLORTERM", "24bit")])));
+        assert_eq!(Some(ColorDepth::Ansi256), ColorDepth::from_name("256"));
+        assert_eq!(None, ColorDepth::from_name("16"));
+
+        assert_eq!(16, ansi256(Rgba::rgb(0, 0, 0)));
+        assert_eq!(196, ansi256(Rgba::rgb(255, 0, 0)));
+        assert_eq!(231, ansi256(Rgba::rgb(255, 255, 255)));
+        assert_eq!(244, ansi256(Rgba::rgb(128, 128, 128)));
+        assert_eq!(143, ansi256(Rgba::rgb(189, 183, 107)));
+    }
+
+    #[test]
+    fn test_ansi_renderer() {
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+        let rtp_03 = RangoliTextPattern::new(3, &alphabet_set).unwrap();
+        let glyph_layout = layout::layout_rangoli(&rtp_03, &Monospace, Spacing::Grid, None, 640.0, 480.0);
+        let palette = Palette::default();
+        let still = Animator::still();
+        let scene = Scene::new(&rtp_03, &glyph_layout, &palette, ColorMode::Ring, &still);
+        let render = |depth: ColorDepth, fill: Option<char>, ring_fill: bool| {
+            let mut renderer = AnsiRenderer::new(Vec::new(), fill, depth, ring_fill);
+            renderer.render(&scene).unwrap();
+            String::from_utf8(renderer.into_inner()).unwrap()
+        };
+
+        // Without colors it prints exactly what TerminalRenderer prints.
+
+        for fill in [Some('-'), None] {
+            let mut plain = TerminalRenderer::new(Vec::new(), fill);
+            plain.render(&scene).unwrap();
+            assert_eq!(String::from_utf8(plain.into_inner()).unwrap(), render(ColorDepth::Mono, fill, true));
+        }
+
+        // Tokens in the palette color, delimiters dimmed towards the background,
+        // and the padding left uncolored.
+
+        let truecolor = render(ColorDepth::TrueColor, Some('-'), false);
+        assert!(truecolor.starts_with("----\x1b[38;2;189;183;107mc\x1b[0m----\n"));
+        assert!(truecolor.contains("\x1b[38;2;164;126;63m-\x1b[0m"));
+        assert_eq!(5, truecolor.lines().count());
+
+        let ansi256 = render(ColorDepth::Ansi256, Some('-'), false);
+        assert!(ansi256.starts_with("----\x1b[38;5;143mc\x1b[0m----\n"));
+
+        let filled = render(ColorDepth::TrueColor, None, true);
+        assert!(filled.starts_with("\x1b[38;2;189;183;107m\x1b[48;2;154;103;45mc\x1b[0m\n"));
+    }
+
+    #[test]
+    fn test_play() {
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+        let rtp_03 = RangoliTextPattern::new(3, &alphabet_set).unwrap();
+        let glyph_layout = layout::layout_rangoli(&rtp_03, &Monospace, Spacing::Grid, None, 640.0, 480.0);
+        let palette = Palette::default();
+        let reveal = Animator::new(AnimationMode::Reveal, 100.0, false);
+
+        // Six rings' worth of time at 100 rings and 100 frames per second. Every
+        // frame after the first moves back up over the one before and clears
+        // what is left of its lines.
+
+        let mut renderer = AnsiRenderer::new(Vec::new(), Some('.'), ColorDepth::Mono, false);
+        play(&mut renderer, &rtp_03, &glyph_layout, &palette, ColorMode::Ring, &reveal, 100, 0.06).unwrap();
+        let output = String::from_utf8(renderer.into_inner()).unwrap();
+
+        assert_eq!(5, output.matches("\x1b[5A\r").count());
+        assert!(output.starts_with(".........\n.........\n....a....\n"));
+        assert!(output.ends_with("\x1b[5A\r....c....\x1b[K\n..c-b-c..\x1b[K\nc-b-a-b-c\x1b[K\n..c-b-c..\x1b[K\n....c....\x1b[K\n"));
+
+        // Piped or redirected, only the final frame is written, as it is.
+
+        let mut renderer = AnsiRenderer::new(Vec::new(), Some('.'), ColorDepth::Mono, false);
+        renderer.set_in_place(false);
+        play(&mut renderer, &rtp_03, &glyph_layout, &palette, ColorMode::Ring, &reveal, 100, 0.06).unwrap();
+        let output = String::from_utf8(renderer.into_inner()).unwrap();
+        assert_eq!("....c....\n..c-b-c..\nc-b-a-b-c\n..c-b-c..\n....c....\n", output);
+
+        let mut renderer = AnsiRenderer::new(Vec::new(), Some('.'), ColorDepth::TrueColor, false);
+        renderer.set_in_place(false);
+        play(&mut renderer, &rtp_03, &glyph_layout, &palette, ColorMode::Ring, &reveal, 100, 0.06).unwrap();
+        let output = String::from_utf8(renderer.into_inner()).unwrap();
+        assert_eq!(5, output.lines().count());
+        assert!(!output.contains("A\r") && !output.contains("\x1b[K"));
+    }
+}
@@ -0,0 +1,320 @@
+use crate::error::RangoliError;
+use crate::rangoli::{AlphabetSet, Cell, RangoliGrid, RangoliTextPattern, Script};
+
+/// One step of a transformation. Steps are applied in order, each to the
+/// output of the one before, with RangoliTextPattern::transform().
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transform {
+    // The token of the outer ring at the center, and so on outwards.
+    Invert,
+    // Every token replaced by the one so many places further on in the
+    // alphabet, wrapping around at its end.
+    ShiftRings(i32),
+    // Left and right swapped.
+    Mirror,
+    // A quarter turn clockwise.
+    Rotate90,
+    // An eighth turn clockwise. The rotated lattice is √2 coarser, so every
+    // other position of the result is left empty.
+    Rotate45,
+    // Latin letters shifted through the alphabet, keeping their case.
+    Caesar(i32),
+    // The i-th token of the alphabet replaced by the i-th token of a script.
+    Transliterate(Script),
+}
+
+impl Transform {
+    pub const NAMES: [&'static str; 7] = [
+        "invert", "shift:N", "mirror", "rotate-90", "rotate-45", "caesar:N", "transliterate:SCRIPT",
+    ];
+
+    /// Parses a step such as "invert", "shift:2", "caesar:-3" or
+    /// "transliterate:greek".
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase();
+        let (name, arg) = match name.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (name.as_str(), None),
+        };
+
+        match (name, arg) {
+            ("invert", None) => Some(Transform::Invert),
+            ("shift", Some(arg)) => arg.parse().ok().map(Transform::ShiftRings),
+            ("mirror", None) => Some(Transform::Mirror),
+            ("rotate-90", None) => Some(Transform::Rotate90),
+            ("rotate-45", None) => Some(Transform::Rotate45),
+            ("caesar", Some(arg)) => arg.parse().ok().map(Transform::Caesar),
+            ("transliterate", Some(arg)) => Script::from_name(arg).map(Transform::Transliterate),
+            _ => None,
+        }
+    }
+}
+
+impl RangoliGrid {
+    /// Applies a single step, see Transform.
+    pub fn transform(&self, transform: Transform) -> Result<Self, RangoliError> {
+        match transform {
+            Transform::Invert => Ok(self.invert()),
+            Transform::ShiftRings(shift) => Ok(self.shift_rings(shift)),
+            Transform::Mirror => Ok(self.mirror()),
+            Transform::Rotate90 => Ok(self.rotate_90()),
+            Transform::Rotate45 => Ok(self.rotate_45()),
+            Transform::Caesar(shift) => self.remap(|token| caesar(token, shift)),
+            Transform::Transliterate(script) => self.transliterate(script),
+        }
+    }
+
+    /// Draws ring r with the token ring (rings - 1 - r) is drawn with, so the
+    /// token of the outer ring ends up at the center.
+    pub fn invert(&self) -> Self {
+        let (token_cols, mut tokens) = self.token_lattice();
+        let rings = tokens.iter().flatten().map(|cell| cell.ring + 1).max().unwrap_or(0);
+
+        let mut ring_tokens = vec![0; rings];
+        for cell in tokens.iter().flatten() {
+            ring_tokens[cell.ring] = cell.token;
+        }
+        for cell in tokens.iter_mut().flatten() {
+            cell.token = ring_tokens[rings - 1 - cell.ring];
+        }
+
+        Self::from_token_lattice(self.rows(), token_cols, &tokens, self.get_alphabet_set().clone())
+    }
+
+    /// Replaces every token by the one shift places further on in the
+    /// alphabet, wrapping around. A negative shift goes backwards.
+    pub fn shift_rings(&self, shift: i32) -> Self {
+        let (token_cols, mut tokens) = self.token_lattice();
+        let len = self.get_alphabet_set().len() as i64;
+
+        for cell in tokens.iter_mut().flatten() {
+            cell.token = (cell.token as i64 + shift as i64).rem_euclid(len) as usize;
+        }
+
+        Self::from_token_lattice(self.rows(), token_cols, &tokens, self.get_alphabet_set().clone())
+    }
+
+    /// Swaps left and right.
+    pub fn mirror(&self) -> Self {
+        let (token_cols, tokens) = self.token_lattice();
+        let mirrored = tokens
+            .chunks(token_cols)
+            .flat_map(|row| row.iter().rev().copied())
+            .collect::<Vec<Option<Cell>>>();
+
+        Self::from_token_lattice(self.rows(), token_cols, &mirrored, self.get_alphabet_set().clone())
+    }
+
+    /// Turns the pattern a quarter clockwise. Token rows become token
+    /// columns, so a hexagonal pattern comes out taller than it is wide.
+    pub fn rotate_90(&self) -> Self {
+        let (token_cols, tokens) = self.token_lattice();
+        let rows = self.rows();
+
+        // The token at (row, t) moves to (t, rows - 1 - row).
+
+        let rotated = (0..token_cols * rows)
+            .map(|pos| tokens[(rows - 1 - pos % rows) * token_cols + pos / rows])
+            .collect::<Vec<Option<Cell>>>();
+
+        Self::from_token_lattice(token_cols, rows, &rotated, self.get_alphabet_set().clone())
+    }
+
+    /// Turns the pattern an eighth clockwise, so a diamond becomes a square
+    /// and a square a diamond. Positions (x, y) from the center move to
+    /// (x - y, x + y), which leaves every other position empty. When every
+    /// position left is even, as after two eighth turns, the lattice is
+    /// halved again.
+    ///
+    /// The result is a checkerboard: tokens only sit where x + y is even,
+    /// with no delimiter between them. padded_lines('-') of the diamond of
+    /// size 3 turns into
+    ///
+    /// ```text
+    /// c---c---c
+    /// --b---b--
+    /// c---a---c
+    /// --b---b--
+    /// c---c---c
+    /// ```
+    ///
+    /// The trimmed lines drop the padding at either end only, so the gaps
+    /// stay in the false token: "c   c   c", "b   b" and so on.
+    pub fn rotate_45(&self) -> Self {
+        let (token_cols, tokens) = self.token_lattice();
+        let (center_row, center_col) = (self.rows() as i64 / 2, token_cols as i64 / 2);
+
+        let mut rotated = tokens
+            .iter()
+            .enumerate()
+            .filter_map(|(pos, cell)| cell.map(|cell| (pos, cell)))
+            .map(|(pos, cell)| {
+                let x = (pos % token_cols) as i64 - center_col;
+                let y = (pos / token_cols) as i64 - center_row;
+                (x - y, x + y, cell)
+            })
+            .collect::<Vec<(i64, i64, Cell)>>();
+
+        if rotated.iter().all(|(x, y, _)| x % 2 == 0 && y % 2 == 0) {
+            for (x, y, _) in rotated.iter_mut() {
+                *x /= 2;
+                *y /= 2;
+            }
+        }
+
+        // The center stays in the middle of the new lattice.
+
+        let half_width = rotated.iter().map(|(x, _, _)| x.abs()).max().unwrap_or(0);
+        let half_height = rotated.iter().map(|(_, y, _)| y.abs()).max().unwrap_or(0);
+        let (rows, token_cols) = ((2 * half_height + 1) as usize, (2 * half_width + 1) as usize);
+
+        let mut lattice = vec![None; rows * token_cols];
+        for (x, y, cell) in rotated {
+            lattice[(y + half_height) as usize * token_cols + (x + half_width) as usize] = Some(cell);
+        }
+
+        Self::from_token_lattice(rows, token_cols, &lattice, self.get_alphabet_set().clone())
+    }
+
+    /// Replaces every token of the alphabet by f(token), for a substitution
+    /// table or a transliteration. Fails when a new token is not valid, or
+    /// clashes with the delimiter.
+    pub fn remap<F: Fn(&str) -> String>(&self, f: F) -> Result<Self, RangoliError> {
+        let alphabet_set = self.get_alphabet_set();
+        let alphabet = AlphabetSet::from_tokens_with_delimiter(
+            alphabet_set.get_alphabet().iter().map(|token| f(token)).collect::<Vec<String>>(),
+            alphabet_set.get_delimiter(),
+        )?;
+
+        Ok(self.with_alphabet(alphabet))
+    }
+
+    /// Replaces the i-th token of the alphabet by the i-th token of the
+    /// script. Fails when a token on the grid has no counterpart.
+    pub fn transliterate(&self, script: Script) -> Result<Self, RangoliError> {
+        let targets = script.tokens();
+        if let Some(needed) = self.token_lattice().1.iter().flatten().map(|cell| cell.token + 1).max() {
+            if needed > targets.len() {
+                return Err(RangoliError::AlphabetTooShort { needed, available: targets.len() });
+            }
+        }
+
+        let alphabet = self.get_alphabet_set();
+        self.remap(|token| match alphabet.position(token).and_then(|i| targets.get(i)) {
+            Some(target) => target.to_string(),
+            None => token.to_string(),
+        })
+    }
+}
+
+impl RangoliTextPattern {
+    /// Derives a variation of the pattern by applying the steps in order.
+    /// The result is a pattern like any other, so every renderer takes it.
+    pub fn transform(&self, transforms: &[Transform]) -> Result<Self, RangoliError> {
+        let mut grid = self.get_grid().clone();
+        for transform in transforms {
+            grid = grid.transform(*transform)?;
+        }
+        Ok(self.with_grid(grid))
+    }
+}
+
+// Shifts the Latin letters of a token, keeping their case.
+
+fn caesar(token: &str, shift: i32) -> String {
+    token
+        .chars()
+        .map(|c| {
+            let base = match c {
+                'a'..='z' => b'a',
+                'A'..='Z' => b'A',
+                _ => return c,
+            };
+            (base + (c as u8 - base + shift.rem_euclid(26) as u8) % 26) as char
+        })
+        .collect::<String>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rangoli::PatternShape;
+
+    #[test]
+    fn test_transforms() {
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+        let rtp_03 = RangoliTextPattern::new(3, &alphabet_set).unwrap();
+
+        let inverted = rtp_03.transform(&[Transform::Invert]).unwrap();
+        assert_eq!(vec!["a", "a-b-a", "a-b-c-b-a", "a-b-a", "a"], inverted.get_rangoli_text().0);
+        assert_eq!(rtp_03, rtp_03.transform(&[Transform::Invert, Transform::Invert]).unwrap());
+
+        let shifted = rtp_03.transform(&[Transform::ShiftRings(2)]).unwrap();
+        assert_eq!("e-d-c-d-e", shifted.get_rangoli_text().0[2]);
+        assert_eq!("b-a-z-a-b", rtp_03.transform(&[Transform::ShiftRings(-1)]).unwrap().get_rangoli_text().0[2]);
+
+        let caesar = rtp_03.transform(&[Transform::Caesar(3)]).unwrap();
+        assert_eq!("f-e-d-e-f", caesar.get_rangoli_text().0[2]);
+        assert_eq!(rtp_03, caesar.transform(&[Transform::Caesar(-3)]).unwrap());
+
+        let greek = rtp_03.transform(&[Transform::Transliterate(Script::GreekLower)]).unwrap();
+        assert_eq!("γ-β-α-β-γ", greek.get_rangoli_text().0[2]);
+        let rtp_26 = RangoliTextPattern::new(26, &alphabet_set).unwrap();
+        assert!(matches!(rtp_26.transform(&[Transform::Transliterate(Script::Digits)]), Err(RangoliError::AlphabetTooShort { needed: 26, .. })));
+
+        // Every stock shape is symmetric, so mirroring changes nothing, and
+        // four quarter turns or eight eighth turns come full circle.
+
+        for name in PatternShape::NAMES {
+            let shape = PatternShape::from_name(name).unwrap();
+            let rtp = RangoliTextPattern::with_shape(4, &alphabet_set, shape).unwrap();
+
+            assert_eq!(rtp, rtp.transform(&[]).unwrap());
+            assert_eq!(rtp, rtp.transform(&[Transform::Mirror]).unwrap());
+            assert_eq!(rtp, rtp.transform(&[Transform::Rotate90; 4]).unwrap());
+            assert_eq!(rtp, rtp.transform(&[Transform::Rotate45; 8]).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_rotations() {
+        let alphabet_set = AlphabetSet::new('a', 'z').unwrap();
+        let rtp_02 = RangoliTextPattern::new(2, &alphabet_set).unwrap();
+
+        let square = rtp_02.transform(&[Transform::Rotate45]).unwrap();
+        assert_eq!(vec!["b...b", "..a..", "b...b"], square.padded_lines('.'));
+        assert_eq!(3, square.rows());
+        assert_eq!(None, square.cell_at(0, 1));
+        assert_eq!(Some(1), square.cell_at(2, 4).map(|cell| cell.ring));
+
+        // The empty positions of the checkerboard take the fill character.
+        // The trimmed lines leave out those at either end and keep the gaps.
+
+        let rtp_03 = RangoliTextPattern::new(3, &alphabet_set).unwrap();
+        let checkerboard = rtp_03.transform(&[Transform::Rotate45]).unwrap();
+        assert_eq!(vec!["c---c---c", "--b---b--", "c---a---c", "--b---b--", "c---c---c"], checkerboard.padded_lines('-'));
+        let (lines, max_width) = checkerboard.get_rangoli_text();
+        assert_eq!(vec!["c   c   c", "b   b", "c   a   c", "b   b", "c   c   c"], lines);
+        assert_eq!(9, max_width);
+        assert_eq!(vec!["a", "b", "c"], checkerboard.get_grid().used_tokens());
+
+        let hexagonal = RangoliTextPattern::with_shape(2, &alphabet_set, PatternShape::Hexagonal).unwrap();
+        let turned = hexagonal.transform(&[Transform::Rotate90]).unwrap();
+        assert_eq!((5, 5), (turned.rows(), turned.cols()));
+        assert_eq!(vec!["--b--", "b-b-b", "b-a-b", "b-b-b", "--b--"], turned.padded_lines('-'));
+
+        let rtp_01 = RangoliTextPattern::new(1, &alphabet_set).unwrap();
+        assert_eq!(rtp_01, rtp_01.transform(&[Transform::Rotate45, Transform::Rotate90, Transform::Invert]).unwrap());
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Some(Transform::Invert), Transform::from_name("invert"));
+        assert_eq!(Some(Transform::ShiftRings(-2)), Transform::from_name("shift:-2"));
+        assert_eq!(Some(Transform::Rotate45), Transform::from_name("Rotate-45"));
+        assert_eq!(Some(Transform::Transliterate(Script::Hiragana)), Transform::from_name("transliterate:hiragana"));
+        assert_eq!(None, Transform::from_name("shift"));
+        assert_eq!(None, Transform::from_name("caesar:x"));
+        assert_eq!(None, Transform::from_name("invert:1"));
+    }
+}
@@ -0,0 +1,176 @@
+use std::{fs, io, path::Path};
+
+use ab_glyph::{point, Font, FontVec, PxScale, ScaleFont};
+
+use crate::metrics::FontMetrics;
+use crate::palette::Rgba;
+use crate::raster::{self, Framebuffer, RasterFont};
+
+// Raylib's LoadFont() rasterizes TrueType fonts at this size. See rtext.c
+// FONT_TTF_DEFAULT_SIZE.
+pub const TTF_BASE_SIZE: usize = 32;
+const FALLBACK_CHAR: char = '?';
+
+/// A TrueType or OpenType font read on the CPU. Advances are taken at the
+/// base size the way raylib's LoadFontData() does, so text measures the same
+/// as it does once raylib has loaded the font at that size.
+pub struct TrueTypeFont {
+    font: FontVec,
+    base_size: usize,
+}
+
+impl TrueTypeFont {
+    pub fn load<P: AsRef<Path>>(path: P, base_size: usize) -> io::Result<Self> {
+        Self::from_bytes(fs::read(path)?, base_size)
+    }
+
+    pub fn from_bytes(data: Vec<u8>, base_size: usize) -> io::Result<Self> {
+        let font = FontVec::try_from_vec(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self { font, base_size: base_size.max(1) })
+    }
+
+    // Pixels per font unit for a given pixel height, as stb_truetype's
+    // stbtt_ScaleForPixelHeight() computes it.
+
+    fn scale_for_pixel_height(&self, pixel_height: f32) -> f32 {
+        pixel_height / self.font.height_unscaled()
+    }
+}
+
+impl FontMetrics for TrueTypeFont {
+    fn base_size(&self) -> f32 {
+        self.base_size as f32
+    }
+
+    // Glyph 0 is the font's .notdef box, so it does not count.
+    fn has_glyph(&self, c: char) -> bool {
+        self.font.glyph_id(c).0 != 0
+    }
+
+    fn advance(&self, c: char) -> f32 {
+        let c = if self.has_glyph(c) { c } else { FALLBACK_CHAR };
+        let advance = self.font.h_advance_unscaled(self.font.glyph_id(c));
+
+        // Raylib keeps whole pixels.
+        (advance * self.scale_for_pixel_height(self.base_size as f32)).trunc()
+    }
+
+    // The outline's bounding box, widened to the whole pixels of the bitmap
+    // stb_truetype renders for it.
+    fn ink_bounds(&self, c: char) -> Option<(f32, f32)> {
+        let c = if self.has_glyph(c) { c } else { FALLBACK_CHAR };
+        let bounds = self.font.outline(self.font.glyph_id(c))?.bounds;
+        let scale = self.scale_for_pixel_height(self.base_size as f32);
+
+        Some(((bounds.min.x * scale).floor(), (bounds.max.x * scale).ceil()))
+    }
+}
+
+// Outlines are rasterized straight at the drawing size rather than scaled up
+// from the base size, so large glyphs stay sharp. The baseline sits one
+// ascent below the top of the line, as in raylib.
+
+impl RasterFont for TrueTypeFont {
+    fn draw_glyph(&self, framebuffer: &mut Framebuffer, c: char, x: f32, y: f32, font_size: f32, tint: Rgba) {
+        let c = if self.has_glyph(c) { c } else { FALLBACK_CHAR };
+        let scale = PxScale::from(font_size);
+        let ascent = self.font.as_scaled(scale).ascent();
+        let glyph = self.font.glyph_id(c).with_scale_and_position(scale, point(x, y + ascent));
+
+        let outlined = match self.font.outline_glyph(glyph) {
+            Some(outlined) => outlined,
+            None => return,
+        };
+        let bounds = outlined.px_bounds();
+
+        outlined.draw(|gx, gy, coverage| {
+            let (px, py) = (bounds.min.x + gx as f32, bounds.min.y + gy as f32);
+            if px < 0.0 || py < 0.0 {
+                return;
+            }
+            let alpha = raster::mul_u8((coverage.clamp(0.0, 1.0) * 255.0).round() as u8, tint.a);
+            framebuffer.blend(px as usize, py as usize, [tint.r, tint.g, tint.b, alpha]);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmap_font::BitmapFont;
+    use crate::fallback::FontChain;
+    use crate::fonts;
+
+    // DejaVu Sans Mono cut down to the glyphs drawn here, see tests/fonts.
+    const TEST_FONT: &[u8] = include_bytes!("../tests/fonts/DejaVuSansMono-subset.ttf");
+
+    #[test]
+    fn test_truetype_font() {
+        let font = TrueTypeFont::from_bytes(TEST_FONT.to_vec(), TTF_BASE_SIZE).unwrap();
+
+        assert!(font.has_glyph('a'));
+        assert!(font.has_glyph('α'));
+        assert!(!font.has_glyph('\u{e000}'));
+        assert_eq!(font.advance('?'), font.advance('\u{e000}'));
+
+        // A monospace font advances every glyph by the same whole number of pixels.
+        let advance = font.advance('m');
+        assert!(advance > 0.0 && advance == advance.trunc());
+        assert_eq!(advance, font.advance('i'));
+        assert_eq!(advance * 3.0 * 2.0 + 2.0 * 3.0, font.measure_text("mmi", 2.0 * TTF_BASE_SIZE as f32, 3.0));
+
+        // The stroke of an 'i' sits inside its advance, the 'm' fills more of it.
+        let (i_left, i_right) = font.ink_bounds('i').unwrap();
+        let (m_left, m_right) = font.ink_bounds('m').unwrap();
+        assert!(0.0 < i_left && i_right < advance);
+        assert!(i_right - i_left < m_right - m_left);
+        assert_eq!(None, font.ink_bounds(' '));
+        assert!(TrueTypeFont::from_bytes(b"not a font".to_vec(), TTF_BASE_SIZE).is_err());
+    }
+
+    #[test]
+    fn test_draw_glyph() {
+        let font = TrueTypeFont::from_bytes(TEST_FONT.to_vec(), TTF_BASE_SIZE).unwrap();
+        let background = Rgba::new(0, 0, 0, 255);
+        let inked = |fb: &Framebuffer| fb.pixels().chunks(4).filter(|px| px[0] > 0).count();
+
+        // Doubling the size roughly quadruples the ink, and a space has none.
+        let mut small = Framebuffer::new(64, 64, background);
+        small.draw_glyph(&font, 'm', 0.0, 0.0, 16.0, Rgba::new(255, 255, 255, 255));
+        let mut large = Framebuffer::new(64, 64, background);
+        large.draw_glyph(&font, 'm', 0.0, 0.0, 32.0, Rgba::new(255, 255, 255, 255));
+        assert!(inked(&small) > 0);
+        assert!(inked(&large) > 3 * inked(&small));
+
+        let mut blank = Framebuffer::new(64, 64, background);
+        blank.draw_text(&font, " ", 0.0, 0.0, 32.0, Rgba::new(255, 255, 255, 255));
+        assert_eq!(0, inked(&blank));
+
+        // Glyphs half off the framebuffer are clipped.
+        let mut clipped = Framebuffer::new(8, 8, background);
+        clipped.draw_text(&font, "mm", -4.0, -4.0, 32.0, Rgba::new(255, 255, 255, 255));
+        assert!(inked(&clipped) > 0);
+    }
+
+    #[test]
+    fn test_fallback_chain() {
+        let primary: Box<dyn RasterFont> = Box::new(BitmapFont::load(fonts::bundled_font_path("alagard")).unwrap());
+        let fallback: Box<dyn RasterFont> = Box::new(TrueTypeFont::from_bytes(TEST_FONT.to_vec(), TTF_BASE_SIZE).unwrap());
+        let chain = FontChain::new(primary, vec![fallback]);
+
+        assert_eq!(0, chain.font_index('a'));
+        assert_eq!(1, chain.font_index('α'));
+        assert!(chain.has_glyph('α'));
+        assert!(!chain.has_glyph('\u{e000}'));
+
+        // The bitmap font has no 'α', so the chain draws the TrueType one.
+        let background = Rgba::new(0, 0, 0, 255);
+        let draw = |font: &dyn RasterFont| {
+            let mut framebuffer = Framebuffer::new(64, 64, background);
+            framebuffer.draw_glyph(font, 'α', 8.0, 8.0, 32.0, Rgba::new(255, 255, 255, 255));
+            framebuffer
+        };
+        assert_eq!(draw(&chain.fonts()[1]).pixels(), draw(&chain).pixels());
+        assert_ne!(draw(&chain.fonts()[0]).pixels(), draw(&chain).pixels());
+    }
+}